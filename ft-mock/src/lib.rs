@@ -0,0 +1,127 @@
+#![no_std]
+
+//! Minimal fungible token used by the integration tests in place of the real FT program.
+//!
+//! It answers `LogicAction` messages with `FTokenEvent` like the FT program does. Operators,
+//! the deployer and the accounts passed in the init payload, may mint, burn and move tokens
+//! on behalf of any account, the same way the logic contracts are trusted by the FT program.
+
+use gstd::{collections::HashMap as GHashMap, msg, prelude::*, ActorId};
+use io::{FTokenEvent, LogicAction};
+
+#[derive(Default)]
+struct FungibleToken {
+    balances: GHashMap<ActorId, u128>,
+    allowances: GHashMap<(ActorId, ActorId), u128>,
+    operators: Vec<ActorId>,
+}
+
+impl FungibleToken {
+    fn balance_of(&self, account: &ActorId) -> u128 {
+        self.balances.get(account).copied().unwrap_or_default()
+    }
+
+    fn allowance(&self, owner: &ActorId, spender: &ActorId) -> u128 {
+        self.allowances
+            .get(&(*owner, *spender))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn mint(&mut self, recipient: ActorId, amount: u128) -> FTokenEvent {
+        if !self.operators.contains(&msg::source()) {
+            return FTokenEvent::Err;
+        }
+
+        *self.balances.entry(recipient).or_default() += amount;
+        FTokenEvent::Ok
+    }
+
+    fn burn(&mut self, sender: ActorId, amount: u128) -> FTokenEvent {
+        if sender != msg::source() && !self.operators.contains(&msg::source()) {
+            return FTokenEvent::Err;
+        }
+
+        let balance = self.balances.entry(sender).or_default();
+        if *balance < amount {
+            return FTokenEvent::Err;
+        }
+        *balance -= amount;
+        FTokenEvent::Ok
+    }
+
+    fn transfer(&mut self, sender: ActorId, recipient: ActorId, amount: u128) -> FTokenEvent {
+        let spender = msg::source();
+        // Any other spender needs an allowance from the sender
+        let needs_allowance = sender != spender && !self.operators.contains(&spender);
+        if needs_allowance && self.allowance(&sender, &spender) < amount {
+            return FTokenEvent::Err;
+        }
+        if self.balance_of(&sender) < amount {
+            return FTokenEvent::Err;
+        }
+
+        // Only a transfer that goes through uses up the allowance
+        if needs_allowance {
+            *self.allowances.entry((sender, spender)).or_default() -= amount;
+        }
+        *self.balances.entry(sender).or_default() -= amount;
+        *self.balances.entry(recipient).or_default() += amount;
+        FTokenEvent::Ok
+    }
+
+    fn approve(&mut self, approved_account: ActorId, amount: u128) -> FTokenEvent {
+        self.allowances
+            .insert((msg::source(), approved_account), amount);
+        FTokenEvent::Ok
+    }
+}
+
+static mut FUNGIBLE_TOKEN: Option<FungibleToken> = None;
+
+fn fungible_token_mut() -> &'static mut FungibleToken {
+    unsafe { FUNGIBLE_TOKEN.get_or_insert(Default::default()) }
+}
+
+#[no_mangle]
+extern fn init() {
+    let mut operators: Vec<ActorId> = msg::load().expect("Unable to decode operators");
+    operators.push(msg::source());
+
+    unsafe {
+        FUNGIBLE_TOKEN = Some(FungibleToken {
+            operators,
+            ..Default::default()
+        })
+    }
+}
+
+#[no_mangle]
+extern fn handle() {
+    let action: LogicAction = msg::load().expect("Could not load Action");
+    let fungible_token = fungible_token_mut();
+
+    let event = match action {
+        LogicAction::Mint { recipient, amount } => fungible_token.mint(recipient, amount),
+        LogicAction::Burn { sender, amount } => fungible_token.burn(sender, amount),
+        LogicAction::Transfer {
+            sender,
+            recipient,
+            amount,
+        } => fungible_token.transfer(sender, recipient, amount),
+        LogicAction::Approve {
+            approved_account,
+            amount,
+        } => fungible_token.approve(approved_account, amount),
+        // Signed permits are not needed by the contracts under test
+        LogicAction::Permit { .. } => FTokenEvent::Err,
+    };
+
+    msg::reply(event, 0).expect("Failed to reply to FT Action");
+}
+
+#[no_mangle]
+extern fn state() {
+    let account: ActorId = msg::load().expect("Unable to decode account");
+    msg::reply(fungible_token_mut().balance_of(&account), 0).expect("Error in sharing state");
+}
@@ -1,14 +1,29 @@
 #![no_std]
 
-use gstd::{exec, msg, prelude::*, ActorId};
+use gstd::{
+    codec::{Error as CodecError, Input, Output},
+    collections::BTreeMap,
+    exec, msg,
+    prelude::*,
+    scale_info::{build::Fields, Path, Type},
+    ActorId,
+};
 use primitive_types::H512;
 
+pub mod pause;
+pub mod roles;
+
 pub type PostId = u32;
 pub type Timestamp = u64;
 pub type URL = String;
 
 pub type ThreadNode = (PostId, ActorId);
 
+// Tokens minted into a thread's reward pool when it is created
+pub const THREAD_MINT_AMOUNT: u128 = 1;
+// Tokens a user deposits into a thread's reward pool when replying
+pub const REPLY_DEPOSIT: u128 = 1;
+
 #[derive(Encode, Decode, TypeInfo, Clone)]
 #[codec(crate = gstd::codec)]
 #[scale_info(crate = gstd::scale_info)]
@@ -24,7 +39,8 @@ pub struct Post {
 impl Post {
     pub fn new(title: String, content: String, photo_url: String) -> Self {
         Post {
-            post_id: exec::block_height(),
+            // Assigned by the storage contract when the post is pushed
+            post_id: 0,
             posted_at: exec::block_timestamp(),
             owner: msg::source(),
             title,
@@ -46,6 +62,8 @@ pub struct InitThread {
     pub content: String,
     pub photo_url: String,
     pub thread_type: ThreadType,
+    // Lifetime of the thread in blocks, defaults to the duration configured for its type
+    pub duration: Option<u32>,
 }
 
 #[derive(Encode, Decode, TypeInfo)]
@@ -57,7 +75,7 @@ pub struct InitReply {
     pub photo_url: String,
 }
 
-#[derive(Encode, Decode, TypeInfo, Clone)]
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 #[codec(crate = gstd::codec)]
 #[scale_info(crate = gstd::scale_info)]
 pub enum ThreadType {
@@ -65,7 +83,7 @@ pub enum ThreadType {
     Question,
 }
 
-#[derive(Default, Encode, Decode, TypeInfo, Clone)]
+#[derive(Default, Encode, Decode, TypeInfo, Clone, PartialEq, Eq)]
 #[codec(crate = gstd::codec)]
 #[scale_info(crate = gstd::scale_info)]
 pub enum ThreadStatus {
@@ -74,6 +92,31 @@ pub enum ThreadStatus {
     Expired,
 }
 
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, Copy, PartialEq, Eq)]
+#[codec(crate = gstd::codec)]
+#[scale_info(crate = gstd::scale_info)]
+pub enum ReportReason {
+    Spam,
+    Abuse,
+    OffTopic,
+    Other,
+}
+
+#[derive(Debug, Default, Encode, Decode, TypeInfo, Clone, Copy, PartialEq, Eq)]
+#[codec(crate = gstd::codec)]
+#[scale_info(crate = gstd::scale_info)]
+pub enum FeaturedReply {
+    // The reply with the most likes, the earliest one on ties
+    #[default]
+    MostLiked,
+    // The latest reply
+    Newest,
+    // A reply pinned by the thread owner
+    Pinned(PostId),
+    // The answer accepted by the owner of a question thread
+    Accepted(PostId),
+}
+
 #[derive(Decode, Encode, TypeInfo)]
 #[codec(crate = gstd::codec)]
 #[scale_info(crate = gstd::scale_info)]
@@ -98,6 +141,11 @@ pub struct Thread {
     pub thread_type: ThreadType,
     pub distributed_tokens: u128,
     pub graph_rep: ThreadGraph,
+    // Which reply frontends show as the thread's preview
+    pub featured_reply: FeaturedReply,
+    pub reports: u64,
+    // Hidden from frontend queries once reported too often, until a moderator steps in
+    pub hidden: bool,
 }
 
 #[derive(Encode, Decode, TypeInfo, Clone)]
@@ -109,51 +157,160 @@ pub struct ThreadReply {
     pub reports: u64,
     pub like_history: Vec<(ActorId, u128)>,
     pub thread_id: PostId,
+    // Hidden from frontend queries once reported too often, until a moderator steps in
+    pub hidden: bool,
 }
 
-#[derive(Encode, Decode, TypeInfo, Clone)]
-#[codec(crate = gstd::codec)]
-#[scale_info(crate = gstd::scale_info)]
+// Every post of a graph with the replies to it, in post order
+type AdjacencyList = Vec<(ThreadNode, Vec<ThreadNode>)>;
+
+/// Reply tree of a thread, indexed by `PostId` so lookups and path walks do not scan the
+/// whole thread.
+///
+/// It is encoded as `struct ThreadGraph { graph: Vec<(ThreadNode, Vec<ThreadNode>)> }`,
+/// the adjacency list it used to be stored as, so `StorageQueryReply::GraphRep` keeps its
+/// wire format.
+#[derive(Clone)]
 pub struct ThreadGraph {
-    pub graph: Vec<(ThreadNode, Vec<ThreadNode>)>,
+    // Author of every post in the graph
+    pub nodes: BTreeMap<PostId, ActorId>,
+    // Post each reply answers
+    pub parents: BTreeMap<PostId, PostId>,
+    // Replies to each post, in order
+    pub children: BTreeMap<PostId, Vec<PostId>>,
 }
 
 impl ThreadGraph {
     pub fn new() -> Self {
-        ThreadGraph { graph: Vec::new() }
+        ThreadGraph {
+            nodes: BTreeMap::new(),
+            parents: BTreeMap::new(),
+            children: BTreeMap::new(),
+        }
     }
 
     pub fn add_edge(&mut self, from_post_id: PostId, to: ThreadNode) {
-        // Find the node corresponding to the from_post_id
-        for (node, adj_list) in &mut self.graph {
-            if node.0 == from_post_id {
-                // If the node exists, append the new node to its adjacency list
-                adj_list.push(to);
-                return;
-            }
+        // Edges can only start from a node of the graph
+        if !self.nodes.contains_key(&from_post_id) {
+            return;
         }
+
+        self.parents.insert(to.0, from_post_id);
+        self.children.entry(from_post_id).or_default().push(to.0);
     }
 
     pub fn add_node(&mut self, node: ThreadNode) {
-        // Check if the node already exists in the graph
-        for (existing_node, _) in &self.graph {
-            if *existing_node == node {
-                // If it already exists, do nothing
-                return;
+        // An existing node keeps its author
+        self.nodes.entry(node.0).or_insert(node.1);
+    }
+
+    /// Removes a node, its replies answer the post it answered in its place.
+    pub fn remove_node(&mut self, post_id_to_remove: PostId) {
+        self.nodes.remove(&post_id_to_remove);
+        let replies = self.children.remove(&post_id_to_remove).unwrap_or_default();
+
+        let Some(parent) = self.parents.remove(&post_id_to_remove) else {
+            for reply in &replies {
+                self.parents.remove(reply);
+            }
+            return;
+        };
+
+        for reply in &replies {
+            self.parents.insert(*reply, parent);
+        }
+        let siblings = self.children.entry(parent).or_default();
+        match siblings
+            .iter()
+            .position(|post_id| *post_id == post_id_to_remove)
+        {
+            Some(position) => {
+                siblings.splice(position..=position, replies);
             }
+            None => siblings.extend(replies),
         }
-        // If the node does not exist, add it with an empty adjacency list
-        self.graph.push((node, Vec::new()));
     }
 
-    pub fn remove_node(&mut self, post_id_to_remove: PostId) {
-        // Remove the node from any adjacency lists
-        for (_, adj_list) in &mut self.graph {
-            adj_list.retain(|node| node.0 != post_id_to_remove);
+    pub fn node(&self, post_id: PostId) -> Option<ThreadNode> {
+        self.nodes.get(&post_id).map(|owner| (post_id, *owner))
+    }
+
+    /// Returns the nodes on the way from `from` down to `to`, both included, or `None` if
+    /// `to` is not a reply in the subtree of `from`.
+    pub fn path(&self, from: PostId, to: PostId) -> Option<Vec<ThreadNode>> {
+        let mut path = vec![self.node(to)?];
+        let mut current = to;
+
+        while current != from {
+            current = *self.parents.get(&current)?;
+            path.push(self.node(current)?);
+
+            // Parents always precede their replies, so a longer walk means a broken graph
+            if path.len() > self.nodes.len() {
+                return None;
+            }
         }
 
-        // Remove the node itself along with its adjacency list
-        self.graph.retain(|(node, _)| node.0 != post_id_to_remove);
+        path.reverse();
+        Some(path)
+    }
+
+    fn to_adjacency_list(&self) -> AdjacencyList {
+        self.nodes
+            .iter()
+            .map(|(post_id, owner)| {
+                let replies = self
+                    .children
+                    .get(post_id)
+                    .map(|children| {
+                        children
+                            .iter()
+                            .filter_map(|child| self.node(*child))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                ((*post_id, *owner), replies)
+            })
+            .collect()
+    }
+
+    fn from_adjacency_list(adjacency_list: AdjacencyList) -> Self {
+        let mut thread_graph = ThreadGraph::new();
+        for (node, _) in &adjacency_list {
+            thread_graph.add_node(*node);
+        }
+        for ((post_id, _), replies) in adjacency_list {
+            for reply in replies {
+                thread_graph.add_edge(post_id, reply);
+            }
+        }
+        thread_graph
+    }
+}
+
+impl Encode for ThreadGraph {
+    fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+        self.to_adjacency_list().encode_to(dest)
+    }
+}
+
+impl Decode for ThreadGraph {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, CodecError> {
+        AdjacencyList::decode(input).map(Self::from_adjacency_list)
+    }
+}
+
+impl TypeInfo for ThreadGraph {
+    type Identity = Self;
+
+    fn type_info() -> Type {
+        Type::builder()
+            .path(Path::new("ThreadGraph", module_path!()))
+            .composite(Fields::named().field(|f| {
+                f.ty::<AdjacencyList>()
+                    .name("graph")
+                    .type_name("Vec<(ThreadNode, Vec<ThreadNode>)>")
+            }))
     }
 }
 
@@ -203,3 +360,66 @@ pub enum FTokenEvent {
     Balance(u128),
     PermitId(u128),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Thread 1 with replies 2 and 3 to it, and reply 4 to reply 2
+    fn thread_graph() -> ThreadGraph {
+        let mut thread_graph = ThreadGraph::new();
+        thread_graph.add_node((1, ActorId::from(1)));
+        for (ref_node, reply_id) in [(1, 2), (1, 3), (2, 4)] {
+            let node = (reply_id, ActorId::from(reply_id as u64));
+            thread_graph.add_node(node);
+            thread_graph.add_edge(ref_node, node);
+        }
+        thread_graph
+    }
+
+    #[test]
+    fn path_leads_from_thread_to_reply() {
+        let thread_graph = thread_graph();
+
+        assert_eq!(
+            thread_graph.path(1, 4),
+            Some(vec![
+                (1, ActorId::from(1)),
+                (2, ActorId::from(2)),
+                (4, ActorId::from(4)),
+            ])
+        );
+        assert_eq!(thread_graph.path(3, 4), None);
+    }
+
+    #[test]
+    fn removed_node_hands_its_replies_to_its_parent() {
+        let mut thread_graph = thread_graph();
+
+        thread_graph.remove_node(2);
+
+        assert_eq!(thread_graph.children.get(&1), Some(&vec![4, 3]));
+        assert_eq!(
+            thread_graph.path(1, 4),
+            Some(vec![(1, ActorId::from(1)), (4, ActorId::from(4))])
+        );
+        assert_eq!(thread_graph.path(1, 3).map(|path| path.len()), Some(2));
+    }
+
+    #[test]
+    fn graph_is_encoded_as_adjacency_list() {
+        let node = |post_id: PostId| (post_id, ActorId::from(post_id as u64));
+        let adjacency_list = vec![
+            (node(1), vec![node(2), node(3)]),
+            (node(2), vec![node(4)]),
+            (node(3), vec![]),
+            (node(4), vec![]),
+        ];
+
+        let encoded = thread_graph().encode();
+        assert_eq!(encoded, adjacency_list.encode());
+
+        let decoded = ThreadGraph::decode(&mut &encoded[..]).expect("Graph decodes");
+        assert_eq!(decoded.path(1, 4), thread_graph().path(1, 4));
+    }
+}
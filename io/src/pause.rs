@@ -0,0 +1,78 @@
+use gstd::prelude::*;
+
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, Copy, PartialEq, Eq)]
+#[codec(crate = gstd::codec)]
+#[scale_info(crate = gstd::scale_info)]
+pub enum PauseScope {
+    // Every action open to users. Featuring replies, reporting posts and claiming refunds
+    // have no scope of their own and only stop with this one
+    All,
+    // New threads and replies
+    Posting,
+    Liking,
+    // Expiring threads and distributing their rewards
+    Settlement,
+}
+
+/// Emergency switches of a contract. Admin actions stay available while paused, so the
+/// contract can be rewired and resumed.
+#[derive(Debug, Default, Encode, Decode, TypeInfo, Clone, PartialEq, Eq)]
+#[codec(crate = gstd::codec)]
+#[scale_info(crate = gstd::scale_info)]
+pub struct PauseFlags {
+    pub all: bool,
+    pub posting: bool,
+    pub liking: bool,
+    pub settlement: bool,
+}
+
+impl PauseFlags {
+    pub fn set(&mut self, scope: PauseScope, paused: bool) {
+        match scope {
+            PauseScope::All => self.all = paused,
+            PauseScope::Posting => self.posting = paused,
+            PauseScope::Liking => self.liking = paused,
+            PauseScope::Settlement => self.settlement = paused,
+        }
+    }
+
+    /// Whether actions of `scope` are halted, either on their own or by the global switch.
+    pub fn is_paused(&self, scope: PauseScope) -> bool {
+        self.all
+            || match scope {
+                PauseScope::All => false,
+                PauseScope::Posting => self.posting,
+                PauseScope::Liking => self.liking,
+                PauseScope::Settlement => self.settlement,
+            }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn global_switch_pauses_every_scope() {
+        let mut pause = PauseFlags::default();
+
+        pause.set(PauseScope::Liking, true);
+        assert!(pause.is_paused(PauseScope::Liking));
+        assert!(!pause.is_paused(PauseScope::Posting));
+        assert!(!pause.is_paused(PauseScope::All));
+
+        pause.set(PauseScope::All, true);
+        for scope in [
+            PauseScope::All,
+            PauseScope::Posting,
+            PauseScope::Liking,
+            PauseScope::Settlement,
+        ] {
+            assert!(pause.is_paused(scope));
+        }
+
+        pause.set(PauseScope::All, false);
+        pause.set(PauseScope::Liking, false);
+        assert_eq!(pause, PauseFlags::default());
+    }
+}
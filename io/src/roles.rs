@@ -0,0 +1,187 @@
+use gstd::{
+    collections::{BTreeMap, BTreeSet},
+    prelude::*,
+    ActorId,
+};
+
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[codec(crate = gstd::codec)]
+#[scale_info(crate = gstd::scale_info)]
+pub enum Role {
+    // Holds every role, manages admins and treasurers
+    Owner,
+    // Configures the contract, manages moderators and can act as one
+    Admin,
+    // Handles reported and removed content
+    Moderator,
+    // Manages reward settings
+    Treasurer,
+}
+
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, PartialEq, Eq)]
+#[codec(crate = gstd::codec)]
+#[scale_info(crate = gstd::scale_info)]
+pub enum RolesError {
+    // Sender does not hold the role required for the action
+    MissingRole(Role),
+    // Ownership changes hands through a transfer, never through grant or revoke
+    OwnerNotGrantable,
+    // Sender is not the pending owner of an ownership transfer
+    NotPendingOwner,
+}
+
+/// Role assignments of a contract. The owner implicitly holds every role, and admins hold
+/// the moderator role.
+#[derive(Default, Encode, Decode, TypeInfo, Clone)]
+#[codec(crate = gstd::codec)]
+#[scale_info(crate = gstd::scale_info)]
+pub struct Roles {
+    pub owner: Option<ActorId>,
+    // Account the owner has offered ownership to, until it accepts
+    pub pending_owner: Option<ActorId>,
+    pub members: BTreeMap<Role, BTreeSet<ActorId>>,
+}
+
+impl Roles {
+    pub fn new(owner: ActorId) -> Self {
+        Roles {
+            owner: Some(owner),
+            pending_owner: None,
+            members: BTreeMap::new(),
+        }
+    }
+
+    pub fn has_role(&self, actor: ActorId, role: Role) -> bool {
+        let is_member = |role: Role| {
+            self.members
+                .get(&role)
+                .is_some_and(|members| members.contains(&actor))
+        };
+
+        self.owner == Some(actor)
+            || is_member(role)
+            || (role == Role::Moderator && is_member(Role::Admin))
+    }
+
+    pub fn ensure_role(&self, actor: ActorId, role: Role) -> Result<(), RolesError> {
+        if !self.has_role(actor, role) {
+            return Err(RolesError::MissingRole(role));
+        }
+        Ok(())
+    }
+
+    pub fn grant(&mut self, caller: ActorId, role: Role, actor: ActorId) -> Result<(), RolesError> {
+        self.ensure_can_manage(caller, role)?;
+        self.members.entry(role).or_default().insert(actor);
+        Ok(())
+    }
+
+    pub fn revoke(
+        &mut self,
+        caller: ActorId,
+        role: Role,
+        actor: ActorId,
+    ) -> Result<(), RolesError> {
+        self.ensure_can_manage(caller, role)?;
+        if let Some(members) = self.members.get_mut(&role) {
+            members.remove(&actor);
+        }
+        Ok(())
+    }
+
+    /// Offers ownership to `new_owner`, which takes over once it calls `accept_ownership`.
+    pub fn transfer_ownership(
+        &mut self,
+        caller: ActorId,
+        new_owner: ActorId,
+    ) -> Result<(), RolesError> {
+        self.ensure_role(caller, Role::Owner)?;
+        self.pending_owner = Some(new_owner);
+        Ok(())
+    }
+
+    pub fn accept_ownership(&mut self, caller: ActorId) -> Result<(), RolesError> {
+        if self.pending_owner != Some(caller) {
+            return Err(RolesError::NotPendingOwner);
+        }
+        self.owner = self.pending_owner.take();
+        Ok(())
+    }
+
+    fn ensure_can_manage(&self, caller: ActorId, role: Role) -> Result<(), RolesError> {
+        match role {
+            Role::Owner => Err(RolesError::OwnerNotGrantable),
+            Role::Admin | Role::Treasurer => self.ensure_role(caller, Role::Owner),
+            Role::Moderator => self.ensure_role(caller, Role::Admin),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OWNER: u64 = 1;
+    const ADMIN: u64 = 2;
+    const USER: u64 = 3;
+
+    fn roles() -> Roles {
+        let mut roles = Roles::new(ActorId::from(OWNER));
+        roles
+            .grant(ActorId::from(OWNER), Role::Admin, ActorId::from(ADMIN))
+            .expect("Owner grants admins");
+        roles
+    }
+
+    #[test]
+    fn owner_holds_every_role() {
+        let roles = roles();
+
+        for role in [Role::Owner, Role::Admin, Role::Moderator, Role::Treasurer] {
+            assert!(roles.has_role(ActorId::from(OWNER), role));
+        }
+        assert!(roles.has_role(ActorId::from(ADMIN), Role::Admin));
+        assert!(roles.has_role(ActorId::from(ADMIN), Role::Moderator));
+        assert!(!roles.has_role(ActorId::from(ADMIN), Role::Treasurer));
+    }
+
+    #[test]
+    fn admins_manage_moderators_only() {
+        let mut roles = roles();
+        let admin = ActorId::from(ADMIN);
+        let user = ActorId::from(USER);
+
+        assert_eq!(roles.grant(admin, Role::Moderator, user), Ok(()));
+        assert!(roles.has_role(user, Role::Moderator));
+        assert_eq!(
+            roles.grant(admin, Role::Treasurer, user),
+            Err(RolesError::MissingRole(Role::Owner))
+        );
+        assert_eq!(
+            roles.grant(ActorId::from(OWNER), Role::Owner, user),
+            Err(RolesError::OwnerNotGrantable)
+        );
+
+        assert_eq!(roles.revoke(admin, Role::Moderator, user), Ok(()));
+        assert!(!roles.has_role(user, Role::Moderator));
+    }
+
+    #[test]
+    fn ownership_transfer_needs_acceptance() {
+        let mut roles = roles();
+        let owner = ActorId::from(OWNER);
+        let user = ActorId::from(USER);
+
+        assert_eq!(roles.transfer_ownership(owner, user), Ok(()));
+        assert!(roles.has_role(owner, Role::Owner));
+        assert_eq!(
+            roles.accept_ownership(ActorId::from(ADMIN)),
+            Err(RolesError::NotPendingOwner)
+        );
+
+        assert_eq!(roles.accept_ownership(user), Ok(()));
+        assert!(roles.has_role(user, Role::Owner));
+        assert!(!roles.has_role(owner, Role::Owner));
+        assert_eq!(roles.pending_owner, None);
+    }
+}
@@ -1,53 +1,253 @@
 #![no_std]
 
-use gmeta::{InOut, Metadata, Out};
-use gstd::{msg, prelude::*, ActorId};
+use gmeta::{In, InOut, Metadata, Out};
+use gstd::{
+    collections::{BTreeMap, BTreeSet},
+    exec, msg,
+    prelude::*,
+    ActorId,
+};
+use io::pause::{PauseFlags, PauseScope};
+use io::roles::{Role, Roles, RolesError};
 use io::{FTokenEvent, LogicAction};
-use io::{InitReply, InitThread, Post, PostId, Thread, ThreadReply};
-use reward_logic_io::{RewardLogicAction, RewardLogicEvent};
-use storage_io::{StorageAction, StorageEvent};
+use io::{
+    FeaturedReply, InitReply, InitThread, Post, PostId, ReportReason, Thread, ThreadReply,
+    ThreadStatus, ThreadType, REPLY_DEPOSIT, THREAD_MINT_AMOUNT,
+};
+use reward_logic_io::{RewardLogicAction, RewardLogicError, RewardLogicEvent, SettlementOutcome};
+use storage_io::{StorageAction, StorageError, StorageEvent, StorageQuery, StorageQueryReply};
 
-#[derive(Default, Encode, Decode, TypeInfo)]
+// Default lifetime of a thread: 7 days of 3 second blocks
+pub const DEFAULT_THREAD_DURATION: u32 = 7 * 24 * 60 * 60 / 3;
+// Default gas given to a scheduled expiry, which has to cover the whole settlement
+pub const DEFAULT_EXPIRY_GAS_LIMIT: u64 = 100_000_000_000;
+
+#[derive(Encode, Decode, TypeInfo)]
 #[codec(crate = gstd::codec)]
 #[scale_info(crate = gstd::scale_info)]
 pub struct ThreadLogic {
-    pub admin: Option<ActorId>,
+    pub roles: Roles,
+    pub pause: PauseFlags,
     pub address_ft: Option<ActorId>,
     pub address_storage: Option<ActorId>,
     pub address_reward_logic: Option<ActorId>,
+    // Default thread lifetimes in blocks
+    pub challenge_duration: u32,
+    pub question_duration: u32,
+    // Block height at which each active thread is scheduled to expire, kept until the
+    // thread is settled
+    pub thread_expiries: BTreeMap<PostId, u32>,
+    // Thread expired by each scheduled `ExpireScheduled` message
+    pub scheduled_expiries: BTreeMap<u64, PostId>,
+    pub next_expiry_id: u64,
+    pub expiry_gas_limit: u64,
+    // Threads no scheduled expiry will settle, as it fell into a settlement pause or could
+    // not be sent. They are left for an admin to expire with `ExpireThread`
+    pub manual_expiries: BTreeSet<PostId>,
+    // Deposits that could not be sent back after a failed reply or like, claimable with
+    // `ClaimRefund`
+    pub pending_refunds: BTreeMap<ActorId, u128>,
 }
 
 impl ThreadLogic {
     pub fn new() -> Self {
         ThreadLogic {
-            admin: None,
+            roles: Roles::default(),
+            pause: PauseFlags::default(),
             address_ft: None,
             address_storage: None,
             address_reward_logic: None,
+            challenge_duration: DEFAULT_THREAD_DURATION,
+            question_duration: DEFAULT_THREAD_DURATION,
+            thread_expiries: BTreeMap::new(),
+            scheduled_expiries: BTreeMap::new(),
+            next_expiry_id: 0,
+            expiry_gas_limit: DEFAULT_EXPIRY_GAS_LIMIT,
+            manual_expiries: BTreeSet::new(),
+            pending_refunds: BTreeMap::new(),
+        }
+    }
+
+    pub fn default_duration(&self, thread_type: ThreadType) -> u32 {
+        match thread_type {
+            ThreadType::Challenge => self.challenge_duration,
+            ThreadType::Question => self.question_duration,
+        }
+    }
+
+    pub fn set_default_duration(&mut self, thread_type: ThreadType, duration: u32) {
+        match thread_type {
+            ThreadType::Challenge => self.challenge_duration = duration,
+            ThreadType::Question => self.question_duration = duration,
+        }
+    }
+
+    pub fn ensure_role(&self, caller: ActorId, role: Role) -> Result<(), ThreadLogicError> {
+        self.roles
+            .ensure_role(caller, role)
+            .map_err(ThreadLogicError::Roles)
+    }
+
+    pub fn ensure_not_paused(&self, scope: PauseScope) -> Result<(), ThreadLogicError> {
+        if self.pause.is_paused(scope) {
+            return Err(ThreadLogicError::Paused(scope));
         }
+        Ok(())
+    }
+
+    pub fn ft_address(&self) -> Result<ActorId, ThreadLogicError> {
+        self.address_ft
+            .ok_or(ThreadLogicError::AddressNotConfigured)
+    }
+
+    pub fn storage_address(&self) -> Result<ActorId, ThreadLogicError> {
+        self.address_storage
+            .ok_or(ThreadLogicError::AddressNotConfigured)
+    }
+
+    pub fn reward_logic_address(&self) -> Result<ActorId, ThreadLogicError> {
+        self.address_reward_logic
+            .ok_or(ThreadLogicError::AddressNotConfigured)
     }
 
-    pub async fn mint_tokens(&mut self, amount: u128) -> Result<(), ()> {
+    /// Schedules a delayed `ExpireScheduled` message to this program, which settles the
+    /// thread once `duration` blocks have passed. The message carries its own gas, as nobody
+    /// pays for it when it is delivered.
+    pub fn schedule_expiry(
+        &mut self,
+        thread_id: PostId,
+        duration: u32,
+    ) -> Result<(), ThreadLogicError> {
+        let expiry_id = self.next_expiry_id;
+
+        msg::send_delayed_with_gas(
+            exec::program_id(),
+            ThreadLogicAction::ExpireScheduled(expiry_id),
+            self.expiry_gas_limit,
+            0,
+            duration,
+        )
+        .map_err(|_| ThreadLogicError::MessageFailed)?;
+
+        self.scheduled_expiries.insert(expiry_id, thread_id);
+        self.next_expiry_id += 1;
+        Ok(())
+    }
+
+    async fn send_storage_action(
+        &self,
+        action: StorageAction,
+    ) -> Result<StorageEvent, ThreadLogicError> {
+        msg::send_for_reply_as::<_, Result<StorageEvent, StorageError>>(
+            self.storage_address()?,
+            action,
+            0,
+            0,
+        )
+        .map_err(|_| ThreadLogicError::MessageFailed)?
+        .await
+        .map_err(|_| ThreadLogicError::MessageFailed)?
+        .map_err(ThreadLogicError::Storage)
+    }
+
+    async fn query_storage(
+        &self,
+        query: StorageQuery,
+    ) -> Result<StorageQueryReply, ThreadLogicError> {
+        msg::send_for_reply_as::<_, Result<StorageQueryReply, StorageError>>(
+            self.storage_address()?,
+            StorageAction::Query(query),
+            0,
+            0,
+        )
+        .map_err(|_| ThreadLogicError::MessageFailed)?
+        .await
+        .map_err(|_| ThreadLogicError::MessageFailed)?
+        .map_err(ThreadLogicError::Storage)
+    }
+
+    /// Checks that the deadline of the thread has not passed, even when its scheduled expiry
+    /// has not settled it yet.
+    pub fn ensure_before_deadline(&self, thread_id: PostId) -> Result<(), ThreadLogicError> {
+        match self.thread_expiries.get(&thread_id) {
+            Some(deadline) if *deadline <= exec::block_height() => {
+                Err(ThreadLogicError::ThreadExpired)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks that the thread still accepts replies and likes, before any tokens are taken
+    /// from the sender.
+    pub async fn ensure_thread_active(&self, thread_id: PostId) -> Result<(), ThreadLogicError> {
+        match self.query_storage(StorageQuery::Thread(thread_id)).await? {
+            StorageQueryReply::Thread(thread) => match thread.thread_status {
+                ThreadStatus::Active => Ok(()),
+                ThreadStatus::Expired => Err(ThreadLogicError::ThreadExpired),
+            },
+            _ => Err(ThreadLogicError::UnexpectedReply),
+        }
+    }
+
+    /// Changes which reply frontends feature for the thread, on behalf of its owner.
+    pub async fn set_featured_reply(
+        &self,
+        thread_id: PostId,
+        featured_reply: FeaturedReply,
+    ) -> Result<ThreadLogicEvent, ThreadLogicError> {
+        let owner = match self.query_storage(StorageQuery::Thread(thread_id)).await? {
+            StorageQueryReply::Thread(thread) => thread.post_data.owner,
+            _ => return Err(ThreadLogicError::UnexpectedReply),
+        };
+        if owner != msg::source() {
+            return Err(ThreadLogicError::NotThreadOwner);
+        }
+
+        match self
+            .send_storage_action(StorageAction::SetFeaturedReply(thread_id, featured_reply))
+            .await?
+        {
+            StorageEvent::FeaturedReplySet => Ok(ThreadLogicEvent::FeaturedReplySet),
+            _ => Err(ThreadLogicError::UnexpectedReply),
+        }
+    }
+
+    /// Reports a thread, or one of its replies if `reply_id` is set, on behalf of the sender.
+    pub async fn report_post(
+        &self,
+        thread_id: PostId,
+        reply_id: Option<PostId>,
+        reason: ReportReason,
+    ) -> Result<ThreadLogicEvent, ThreadLogicError> {
+        let action = match reply_id {
+            None => StorageAction::ReportThread(thread_id, msg::source(), reason),
+            Some(reply_id) => {
+                StorageAction::ReportReply(thread_id, reply_id, msg::source(), reason)
+            }
+        };
+
+        match self.send_storage_action(action).await? {
+            StorageEvent::PostReported(hidden) => Ok(ThreadLogicEvent::PostReported(hidden)),
+            _ => Err(ThreadLogicError::UnexpectedReply),
+        }
+    }
+
+    pub async fn mint_tokens(&mut self, amount: u128) -> Result<(), ThreadLogicError> {
         let res = msg::send_for_reply_as::<_, FTokenEvent>(
-            self.address_ft.expect("Failed to get FT contract address"),
+            self.ft_address()?,
             LogicAction::Mint {
-                recipient: self
-                    .address_storage
-                    .expect("Failed to get Storage contract address"),
+                recipient: self.storage_address()?,
                 amount,
             },
             0,
             0,
         )
-        .expect("Failed to send mint message")
+        .map_err(|_| ThreadLogicError::MessageFailed)?
         .await;
 
         match res {
-            Ok(event) => match event {
-                FTokenEvent::Ok => Ok(()),
-                _ => Err(()),
-            },
-            Err(_) => Err(()),
+            Ok(FTokenEvent::Ok) => Ok(()),
+            _ => Err(ThreadLogicError::FtMintFailed),
         }
     }
 
@@ -57,7 +257,7 @@ impl ThreadLogic {
         amount: u128,
         sender: ActorId,
         recipient: ActorId,
-    ) -> Result<(), ()> {
+    ) -> Result<(), ThreadLogicError> {
         let res = msg::send_for_reply_as::<_, FTokenEvent>(
             ft_address_id,
             LogicAction::Transfer {
@@ -68,19 +268,94 @@ impl ThreadLogic {
             0,
             0,
         )
-        .expect("")
+        .map_err(|_| ThreadLogicError::MessageFailed)?
         .await;
 
         match res {
-            Ok(event) => match event {
-                FTokenEvent::Ok => Ok(()),
-                _ => Err(()),
+            Ok(FTokenEvent::Ok) => Ok(()),
+            _ => Err(ThreadLogicError::FtTransferFailed),
+        }
+    }
+
+    pub async fn burn_tokens(&mut self, amount: u128) -> Result<(), ThreadLogicError> {
+        let res = msg::send_for_reply_as::<_, FTokenEvent>(
+            self.ft_address()?,
+            LogicAction::Burn {
+                sender: self.storage_address()?,
+                amount,
             },
-            Err(_) => Err(()),
+            0,
+            0,
+        )
+        .map_err(|_| ThreadLogicError::MessageFailed)?
+        .await;
+
+        match res {
+            Ok(FTokenEvent::Ok) => Ok(()),
+            _ => Err(ThreadLogicError::FtBurnFailed),
         }
     }
 
-    pub async fn new_thread(&mut self, init_thread: InitThread) {
+    /// Sends tokens already deposited into storage back to `recipient`.
+    pub async fn send_refund(
+        &mut self,
+        amount: u128,
+        recipient: ActorId,
+    ) -> Result<(), ThreadLogicError> {
+        self.transfer_tokens(
+            self.ft_address()?,
+            amount,
+            self.storage_address()?,
+            recipient,
+        )
+        .await
+    }
+
+    /// Compensates a deposit of `recipient` when the storage step following it failed with
+    /// `error`, so the user's tokens are not stranded in storage.
+    ///
+    /// Returns the error to reply with: `error` itself, or `error` wrapped in `RefundFailed`
+    /// when the tokens could not be sent back. They are then kept in `pending_refunds`.
+    pub async fn refund_tokens(
+        &mut self,
+        amount: u128,
+        recipient: ActorId,
+        error: ThreadLogicError,
+    ) -> ThreadLogicError {
+        match self.send_refund(amount, recipient).await {
+            Ok(()) => error,
+            Err(_) => {
+                *self.pending_refunds.entry(recipient).or_default() += amount;
+                ThreadLogicError::RefundFailed(Box::new(error))
+            }
+        }
+    }
+
+    /// Sends the sender the deposits that could not be refunded earlier.
+    pub async fn claim_refund(&mut self) -> Result<ThreadLogicEvent, ThreadLogicError> {
+        let recipient = msg::source();
+        // Taken out before sending, so a claim cannot be paid twice
+        let amount = self
+            .pending_refunds
+            .remove(&recipient)
+            .ok_or(ThreadLogicError::NothingToRefund)?;
+
+        if let Err(error) = self.send_refund(amount, recipient).await {
+            *self.pending_refunds.entry(recipient).or_default() += amount;
+            return Err(error);
+        }
+
+        Ok(ThreadLogicEvent::RefundClaimed(amount))
+    }
+
+    pub async fn new_thread(
+        &mut self,
+        init_thread: InitThread,
+    ) -> Result<ThreadLogicEvent, ThreadLogicError> {
+        let duration = init_thread
+            .duration
+            .unwrap_or_else(|| self.default_duration(init_thread.thread_type));
+
         let post = Post::new(
             init_thread.title,
             init_thread.content,
@@ -94,32 +369,48 @@ impl ThreadLogic {
             distributed_tokens: 0,
             graph_rep: Default::default(),
             replies: Default::default(),
+            featured_reply: Default::default(),
+            reports: 0,
+            hidden: false,
         };
 
-        self.mint_tokens(1).await.expect("");
+        self.mint_tokens(THREAD_MINT_AMOUNT).await?;
 
-        let res = msg::send_for_reply_as::<_, StorageEvent>(
-            self.address_storage
-                .expect("Failed to get storage contract address"),
-            StorageAction::PushThread(thread),
-            0,
-            0,
-        )
-        .expect("Failed to send PushThread message to Storage contract")
-        .await;
+        let res = self
+            .send_storage_action(StorageAction::PushThread(thread))
+            .await
+            .and_then(|event| match event {
+                StorageEvent::ThreadPush(thread_id) => Ok(thread_id),
+                _ => Err(ThreadLogicError::UnexpectedReply),
+            });
 
         match res {
-            Ok(event) => match event {
-                StorageEvent::ThreadPush(_) => {
-                    msg::reply(ThreadLogicEvent::NewThreadCreated, 0).expect("")
+            Ok(thread_id) => {
+                self.thread_expiries
+                    .insert(thread_id, exec::block_height().saturating_add(duration));
+                // Scheduled once the thread exists, so a failed creation spends no gas on an
+                // expiry. The deadline holds even when scheduling fails
+                if self.schedule_expiry(thread_id, duration).is_err() {
+                    self.manual_expiries.insert(thread_id);
                 }
-                _ => msg::reply(ThreadLogicEvent::LogicError, 0).expect(""),
-            },
-            Err(_) => msg::reply(ThreadLogicEvent::LogicError, 0).expect(""),
-        };
+                Ok(ThreadLogicEvent::NewThreadCreated(thread_id))
+            }
+            Err(error) => {
+                // Storage did not accept the thread, take back the tokens minted for its pool
+                match self.burn_tokens(THREAD_MINT_AMOUNT).await {
+                    Ok(()) => Err(error),
+                    Err(_) => Err(ThreadLogicError::RefundFailed(Box::new(error))),
+                }
+            }
+        }
     }
 
-    pub async fn add_reply(&mut self, thread_id: PostId, init_reply: InitReply, ref_node: PostId) {
+    pub async fn add_reply(
+        &mut self,
+        thread_id: PostId,
+        init_reply: InitReply,
+        ref_node: PostId,
+    ) -> Result<ThreadLogicEvent, ThreadLogicError> {
         let post = Post::new(init_reply.title, init_reply.content, init_reply.photo_url);
 
         let reply = ThreadReply {
@@ -128,147 +419,264 @@ impl ThreadLogic {
             like_history: Default::default(),
             likes: 0,
             thread_id,
+            hidden: false,
         };
 
+        self.ensure_before_deadline(thread_id)?;
+        self.ensure_thread_active(thread_id).await?;
+
         self.transfer_tokens(
-            self.address_ft.expect("Address of FT contract not found"),
-            1,
+            self.ft_address()?,
+            REPLY_DEPOSIT,
             msg::source(),
-            self.address_storage
-                .expect("Address of Storage contract not found"),
+            self.storage_address()?,
         )
-        .await
-        .expect("Reply token transfer failed");
+        .await?;
 
-        let res = msg::send_for_reply_as::<_, StorageEvent>(
-            self.address_storage
-                .expect("Failed to get Storage contract address"),
-            StorageAction::PushReply(thread_id, reply, ref_node),
-            0,
-            0,
-        )
-        .expect("Message to Storage contract failed")
-        .await;
+        let res = self
+            .send_storage_action(StorageAction::PushReply(thread_id, reply, ref_node))
+            .await
+            .and_then(|event| match event {
+                StorageEvent::ReplyPush(reply_id) => Ok(reply_id),
+                _ => Err(ThreadLogicError::UnexpectedReply),
+            });
 
         match res {
-            Ok(event) => match event {
-                StorageEvent::ReplyPush(_) => msg::reply(ThreadLogicEvent::ReplyAdded, 0)
-                    .expect("Reply was not correctly added"),
-                _ => msg::reply(ThreadLogicEvent::LogicError, 0).expect(""),
-            },
-            Err(_) => msg::reply(ThreadLogicEvent::LogicError, 0).expect(""),
-        };
+            Ok(reply_id) => Ok(ThreadLogicEvent::ReplyAdded(reply_id)),
+            Err(error) => {
+                // Storage did not accept the reply, give the deposit back to the sender
+                Err(self
+                    .refund_tokens(REPLY_DEPOSIT, msg::source(), error)
+                    .await)
+            }
+        }
     }
 
-    pub async fn like_reply(&mut self, thread_id: PostId, reply_id: PostId, like_count: u128) {
+    pub async fn like_reply(
+        &mut self,
+        thread_id: PostId,
+        reply_id: PostId,
+        like_count: u128,
+    ) -> Result<ThreadLogicEvent, ThreadLogicError> {
+        if like_count == 0 {
+            return Err(ThreadLogicError::InvalidLikeCount);
+        }
+        self.ensure_before_deadline(thread_id)?;
+        self.ensure_thread_active(thread_id).await?;
+
         self.transfer_tokens(
-            self.address_ft.unwrap(),
+            self.ft_address()?,
             like_count,
             msg::source(),
-            self.address_storage.unwrap(),
+            self.storage_address()?,
         )
-        .await
-        .expect("");
+        .await?;
 
-        let res = msg::send_for_reply_as::<_, StorageEvent>(
-            self.address_storage.expect(""),
-            StorageAction::LikeReply(thread_id, reply_id, like_count),
-            0,
-            0,
-        )
-        .expect("")
-        .await;
+        let res = self
+            .send_storage_action(StorageAction::LikeReply(
+                thread_id,
+                reply_id,
+                msg::source(),
+                like_count,
+            ))
+            .await
+            .and_then(|event| match event {
+                StorageEvent::ReplyLiked => Ok(()),
+                _ => Err(ThreadLogicError::UnexpectedReply),
+            });
 
         match res {
-            Ok(event) => match event {
-                StorageEvent::ReplyLiked => msg::reply(ThreadLogicEvent::ReplyLiked, 0).expect(""),
-                _ => msg::reply(ThreadLogicEvent::LogicError, 0).expect(""),
-            },
-            Err(_) => msg::reply(ThreadLogicEvent::LogicError, 0).expect(""),
-        };
+            Ok(()) => Ok(ThreadLogicEvent::ReplyLiked),
+            Err(error) => {
+                // Storage did not accept the like, give the tokens back to the sender
+                Err(self.refund_tokens(like_count, msg::source(), error).await)
+            }
+        }
     }
 
-    pub async fn send_trigger_reward_msg(&mut self, thread_id: PostId) -> Result<(), ()> {
-        let reward_res = msg::send_for_reply_as::<_, RewardLogicEvent>(
-            self.address_reward_logic.expect(""),
+    pub async fn send_trigger_reward_msg(
+        &mut self,
+        thread_id: PostId,
+    ) -> Result<SettlementOutcome, ThreadLogicError> {
+        let event = msg::send_for_reply_as::<_, Result<RewardLogicEvent, RewardLogicError>>(
+            self.reward_logic_address()?,
             RewardLogicAction::TriggerRewardLogic(thread_id),
             0,
             0,
         )
-        .expect("")
-        .await;
+        .map_err(|_| ThreadLogicError::MessageFailed)?
+        .await
+        .map_err(|_| ThreadLogicError::MessageFailed)?
+        .map_err(ThreadLogicError::RewardLogic)?;
 
-        match reward_res {
-            Ok(event) => match event {
-                RewardLogicEvent::RewardLogicTriggered => Ok(()),
-                _ => Err(()),
-            },
-            Err(_) => Err(()),
+        match event {
+            // A retried expiry finds the rewards already distributed
+            RewardLogicEvent::RewardLogicTriggered(outcome)
+            | RewardLogicEvent::AlreadySettled(_, outcome) => Ok(outcome),
+            _ => Err(ThreadLogicError::UnexpectedReply),
         }
     }
 
-    pub async fn send_thread_status_expired_msg(&mut self, thread_id: PostId) -> Result<(), ()> {
-        let res = msg::send_for_reply_as::<_, StorageEvent>(
-            self.address_storage.expect(""),
-            StorageAction::ChangeStatusState(thread_id),
-            0,
-            0,
-        )
-        .expect("")
-        .await;
-
-        match res {
-            Ok(event) => match event {
-                StorageEvent::StatusStateChanged => Ok(()),
-                _ => Err(()),
-            },
-            Err(_) => Err(()),
+    pub async fn send_thread_status_expired_msg(
+        &mut self,
+        thread_id: PostId,
+    ) -> Result<(), ThreadLogicError> {
+        match self
+            .send_storage_action(StorageAction::ChangeStatusState(thread_id))
+            .await?
+        {
+            StorageEvent::StatusStateChanged => Ok(()),
+            _ => Err(ThreadLogicError::UnexpectedReply),
         }
     }
 
-    pub async fn expire_thread(&mut self, thread_id: PostId) {
-        self.send_trigger_reward_msg(thread_id).await.unwrap();
+    /// Expires the thread in storage, then has reward logic distribute its pool. A thread
+    /// left expired by a failed settlement is settled again.
+    pub async fn expire_thread(
+        &mut self,
+        thread_id: PostId,
+    ) -> Result<ThreadLogicEvent, ThreadLogicError> {
+        let status = match self.query_storage(StorageQuery::Thread(thread_id)).await? {
+            StorageQueryReply::Thread(thread) => thread.thread_status,
+            _ => return Err(ThreadLogicError::UnexpectedReply),
+        };
 
-        // Only when reward logic has been successful, change state
-        self.send_thread_status_expired_msg(thread_id)
-            .await
-            .unwrap();
+        match status {
+            // Expired before settling, so no reply or like can reach the pool while it is
+            // being distributed
+            ThreadStatus::Active => self.send_thread_status_expired_msg(thread_id).await?,
+            // Settlement of an earlier expiry did not complete
+            ThreadStatus::Expired if self.thread_expiries.contains_key(&thread_id) => {}
+            ThreadStatus::Expired => return Err(ThreadLogicError::ThreadExpired),
+        }
+
+        let outcome = self.send_trigger_reward_msg(thread_id).await?;
+
+        self.thread_expiries.remove(&thread_id);
+        self.manual_expiries.remove(&thread_id);
+
+        Ok(ThreadLogicEvent::ThreadExpired(outcome))
+    }
+}
+
+impl Default for ThreadLogic {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
+/// Configuration of a new logic contract. Addresses left out can be set later with the
+/// corresponding actions.
+#[derive(Default, Encode, Decode, TypeInfo)]
+#[codec(crate = gstd::codec)]
+#[scale_info(crate = gstd::scale_info)]
+pub struct InitLogic {
+    // Owner of the contract roles, defaults to the deployer
+    pub owner: Option<ActorId>,
+    pub address_ft: Option<ActorId>,
+    pub address_storage: Option<ActorId>,
+    pub address_reward_logic: Option<ActorId>,
+}
+
 #[derive(Encode, Decode, TypeInfo)]
 #[codec(crate = gstd::codec)]
 #[scale_info(crate = gstd::scale_info)]
 pub enum ThreadLogicAction {
+    GrantRole(Role, ActorId),
+    RevokeRole(Role, ActorId),
+    // First step of an ownership transfer, completed by the new owner with `AcceptOwnership`
+    TransferOwnership(ActorId),
+    AcceptOwnership,
     AddAddressFT(ActorId),
     AddAddressStorage(ActorId),
     AddAddressRewardLogic(ActorId),
+    // Default lifetime in blocks for new threads of the given type
+    SetDefaultDuration(ThreadType, u32),
+    // Gas given to the expiries scheduled from now on
+    SetExpiryGasLimit(u64),
+    // Halts or resumes the actions of a scope
+    SetPause(PauseScope, bool),
     NewThread(InitThread),
     AddReply(PostId, InitReply, PostId),
     LikeReply(PostId, PostId, u128),
+    // Sends the sender's deposits that could not be refunded
+    ClaimRefund,
+    // Admin only, expires a thread before its deadline
     ExpireThread(PostId),
+    // Sent by the contract to itself at the deadline of a thread
+    ExpireScheduled(u64),
+    // Thread owner only
+    SetFeaturedReply(PostId, FeaturedReply),
+    ReportThread(PostId, ReportReason),
+    ReportReply(PostId, PostId, ReportReason),
 }
 
 #[derive(Encode, Decode, TypeInfo)]
 #[codec(crate = gstd::codec)]
 #[scale_info(crate = gstd::scale_info)]
 pub enum ThreadLogicEvent {
+    RoleGranted,
+    RoleRevoked,
+    OwnershipTransferStarted,
+    OwnershipTransferred,
     FTAddressAdded,
     StorageAddressAdded,
     RewardLogicAddressAdded,
-    NewThreadCreated,
-    ReplyAdded,
+    DefaultDurationSet,
+    ExpiryGasLimitSet,
+    PauseSet,
+    NewThreadCreated(PostId),
+    ReplyAdded(PostId),
     ReplyLiked,
-    LogicError,
+    RefundClaimed(u128),
+    // The thread's pool was settled as described
+    ThreadExpired(SettlementOutcome),
+    // The thread of a scheduled expiry was already expired
+    StaleExpiryIgnored,
+    FeaturedReplySet,
+    // Whether the reported post is now hidden
+    PostReported(bool),
+}
+
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, PartialEq, Eq)]
+#[codec(crate = gstd::codec)]
+#[scale_info(crate = gstd::scale_info)]
+pub enum ThreadLogicError {
+    Roles(RolesError),
+    // Sender may not perform the action
+    Unauthorized,
+    AddressNotConfigured,
+    // Sending a message or receiving its reply failed
+    MessageFailed,
+    UnexpectedReply,
+    FtMintFailed,
+    FtBurnFailed,
+    FtTransferFailed,
+    // Compensating the failed step did not return the tokens, holds the error of the step
+    RefundFailed(Box<ThreadLogicError>),
+    // Sender has no pending refund
+    NothingToRefund,
+    // Thread no longer accepts replies, likes or settlement
+    ThreadExpired,
+    // Sender does not own the thread
+    NotThreadOwner,
+    // A like gives at least one token
+    InvalidLikeCount,
+    // Actions of the scope are halted by an admin
+    Paused(PauseScope),
+    Storage(StorageError),
+    RewardLogic(RewardLogicError),
 }
 
 pub struct ContractMetadata;
 
 impl Metadata for ContractMetadata {
-    type Init = ();
-    type Handle = InOut<ThreadLogicAction, ThreadLogicEvent>;
+    type Init = In<InitLogic>;
+    type Handle = InOut<ThreadLogicAction, Result<ThreadLogicEvent, ThreadLogicError>>;
     type Reply = ();
     type Others = ();
     type Signal = ();
-    type State = Out<ThreadLogic>; // Logic is stateless, just to save addresses of related contracts
+    // Roles, pause flags, addresses of the related contracts, scheduled thread expiries and
+    // threads left for an admin to expire
+    type State = Out<ThreadLogic>;
 }
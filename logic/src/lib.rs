@@ -1,7 +1,9 @@
 #![no_std]
 
-use gstd::{async_main, msg};
-use logic_io::{ThreadLogic, ThreadLogicAction, ThreadLogicEvent};
+use gstd::{async_main, exec, msg};
+use io::pause::PauseScope;
+use io::roles::{Role, Roles};
+use logic_io::{InitLogic, ThreadLogic, ThreadLogicAction, ThreadLogicError, ThreadLogicEvent};
 
 static mut THREAD_LOGIC: Option<ThreadLogic> = None;
 
@@ -11,68 +13,177 @@ fn thread_logic_mut() -> &'static mut ThreadLogic {
 
 #[no_mangle]
 extern fn init() {
-    let thread_logic = ThreadLogic::new();
+    let init: InitLogic = msg::load().expect("Could not load InitLogic");
 
-    unsafe { THREAD_LOGIC = Some(thread_logic) }
+    let thread_logic = ThreadLogic {
+        roles: Roles::new(init.owner.unwrap_or_else(msg::source)),
+        address_ft: init.address_ft,
+        address_storage: init.address_storage,
+        address_reward_logic: init.address_reward_logic,
+        ..ThreadLogic::new()
+    };
 
-    thread_logic_mut().admin = Some(msg::source());
+    unsafe { THREAD_LOGIC = Some(thread_logic) }
 }
 
 #[async_main]
 async fn main() {
     let action: ThreadLogicAction = msg::load().expect("Could not load Action");
     let thread_logic = thread_logic_mut();
+    let result = process_action(thread_logic, action).await;
+    msg::reply(result, 0).expect("Failed to reply to Thread Logic Action");
+}
+
+async fn process_action(
+    thread_logic: &mut ThreadLogic,
+    action: ThreadLogicAction,
+) -> Result<ThreadLogicEvent, ThreadLogicError> {
     match action {
+        ThreadLogicAction::GrantRole(role, actor) => {
+            thread_logic
+                .roles
+                .grant(msg::source(), role, actor)
+                .map_err(ThreadLogicError::Roles)?;
+            Ok(ThreadLogicEvent::RoleGranted)
+        }
+
+        ThreadLogicAction::RevokeRole(role, actor) => {
+            thread_logic
+                .roles
+                .revoke(msg::source(), role, actor)
+                .map_err(ThreadLogicError::Roles)?;
+            Ok(ThreadLogicEvent::RoleRevoked)
+        }
+
+        ThreadLogicAction::TransferOwnership(new_owner) => {
+            thread_logic
+                .roles
+                .transfer_ownership(msg::source(), new_owner)
+                .map_err(ThreadLogicError::Roles)?;
+            Ok(ThreadLogicEvent::OwnershipTransferStarted)
+        }
+
+        ThreadLogicAction::AcceptOwnership => {
+            thread_logic
+                .roles
+                .accept_ownership(msg::source())
+                .map_err(ThreadLogicError::Roles)?;
+            Ok(ThreadLogicEvent::OwnershipTransferred)
+        }
+
         ThreadLogicAction::AddAddressFT(address) => {
-            if thread_logic.admin.expect("") != msg::source() {
-                panic!("Add Address FT Action can only be called by admin")
-            }
+            thread_logic.ensure_role(msg::source(), Role::Admin)?;
             thread_logic.address_ft = Some(address);
-            msg::reply(ThreadLogicEvent::FTAddressAdded, 0).expect("");
+            Ok(ThreadLogicEvent::FTAddressAdded)
         }
 
         ThreadLogicAction::AddAddressStorage(address) => {
-            if thread_logic.admin.expect("") != msg::source() {
-                panic!("Add Address Storage Action can only be called by admin")
-            }
+            thread_logic.ensure_role(msg::source(), Role::Admin)?;
             thread_logic.address_storage = Some(address);
-            msg::reply(ThreadLogicEvent::StorageAddressAdded, 0).expect("");
+            Ok(ThreadLogicEvent::StorageAddressAdded)
         }
 
         ThreadLogicAction::AddAddressRewardLogic(address) => {
-            if thread_logic.admin.expect("") != msg::source() {
-                panic!("Add Address Reward Logic Action can only be called by admin")
-            }
+            thread_logic.ensure_role(msg::source(), Role::Admin)?;
             thread_logic.address_reward_logic = Some(address);
-            msg::reply(ThreadLogicEvent::RewardLogicAddressAdded, 0).expect("");
+            Ok(ThreadLogicEvent::RewardLogicAddressAdded)
+        }
+
+        ThreadLogicAction::SetDefaultDuration(thread_type, duration) => {
+            thread_logic.ensure_role(msg::source(), Role::Admin)?;
+            thread_logic.set_default_duration(thread_type, duration);
+            Ok(ThreadLogicEvent::DefaultDurationSet)
+        }
+
+        ThreadLogicAction::SetExpiryGasLimit(expiry_gas_limit) => {
+            thread_logic.ensure_role(msg::source(), Role::Admin)?;
+            thread_logic.expiry_gas_limit = expiry_gas_limit;
+            Ok(ThreadLogicEvent::ExpiryGasLimitSet)
+        }
+
+        ThreadLogicAction::SetPause(scope, paused) => {
+            thread_logic.ensure_role(msg::source(), Role::Admin)?;
+            thread_logic.pause.set(scope, paused);
+            Ok(ThreadLogicEvent::PauseSet)
         }
 
-        ThreadLogicAction::NewThread(init_thread) => thread_logic.new_thread(init_thread).await,
+        ThreadLogicAction::NewThread(init_thread) => {
+            thread_logic.ensure_not_paused(PauseScope::Posting)?;
+            thread_logic.new_thread(init_thread).await
+        }
 
         ThreadLogicAction::AddReply(thread_id, init_reply, ref_node) => {
+            thread_logic.ensure_not_paused(PauseScope::Posting)?;
             thread_logic
                 .add_reply(thread_id, init_reply, ref_node)
                 .await
         }
 
         ThreadLogicAction::LikeReply(thread_id, reply_id, like_count) => {
+            thread_logic.ensure_not_paused(PauseScope::Liking)?;
             thread_logic
                 .like_reply(thread_id, reply_id, like_count)
                 .await
         }
 
-        ThreadLogicAction::ExpireThread(thread_id) => thread_logic.expire_thread(thread_id).await,
+        ThreadLogicAction::ClaimRefund => {
+            thread_logic.ensure_not_paused(PauseScope::All)?;
+            thread_logic.claim_refund().await
+        }
+
+        ThreadLogicAction::ExpireThread(thread_id) => {
+            thread_logic.ensure_role(msg::source(), Role::Admin)?;
+            thread_logic.ensure_not_paused(PauseScope::Settlement)?;
+            thread_logic.expire_thread(thread_id).await
+        }
+
+        ThreadLogicAction::ExpireScheduled(expiry_id) => {
+            if msg::source() != exec::program_id() {
+                return Err(ThreadLogicError::Unauthorized);
+            }
+            // The thread may have been expired early by an admin
+            let Some(thread_id) = thread_logic.scheduled_expiries.remove(&expiry_id) else {
+                return Ok(ThreadLogicEvent::StaleExpiryIgnored);
+            };
+            if !thread_logic.thread_expiries.contains_key(&thread_id) {
+                return Ok(ThreadLogicEvent::StaleExpiryIgnored);
+            }
+            // This message cannot carry the gas of another expiry, so one falling into a
+            // pause is not rescheduled. The thread takes no replies or likes past its
+            // deadline and is listed for an admin to expire once settlement resumes
+            if let Err(error) = thread_logic.ensure_not_paused(PauseScope::Settlement) {
+                thread_logic.manual_expiries.insert(thread_id);
+                return Err(error);
+            }
+            thread_logic.expire_thread(thread_id).await
+        }
+
+        ThreadLogicAction::SetFeaturedReply(thread_id, featured_reply) => {
+            thread_logic.ensure_not_paused(PauseScope::All)?;
+            thread_logic
+                .set_featured_reply(thread_id, featured_reply)
+                .await
+        }
+
+        ThreadLogicAction::ReportThread(thread_id, reason) => {
+            thread_logic.ensure_not_paused(PauseScope::All)?;
+            thread_logic.report_post(thread_id, None, reason).await
+        }
+
+        ThreadLogicAction::ReportReply(thread_id, reply_id, reason) => {
+            thread_logic.ensure_not_paused(PauseScope::All)?;
+            thread_logic
+                .report_post(thread_id, Some(reply_id), reason)
+                .await
+        }
     }
 }
 
 #[no_mangle]
 extern fn state() {
-    let thread_logic = unsafe {
-        THREAD_LOGIC
-            .take()
-            .expect("Unexpected error in taking state")
-    };
-    msg::reply::<ThreadLogic>(thread_logic, 0).expect(
+    // Borrow the state, the next `handle` must still find it in place
+    let thread_logic = unsafe { THREAD_LOGIC.as_ref().expect("State is not initialized") };
+    msg::reply::<&ThreadLogic>(thread_logic, 0).expect(
         "Failed to encode or reply with `<ContractMetadata as Metadata>::State` from `state()`",
     );
 }
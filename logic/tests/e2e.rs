@@ -0,0 +1,1341 @@
+use gstd::{ActorId, Encode};
+use gtest::{Program, RunResult, System};
+use io::pause::{PauseFlags, PauseScope};
+use io::roles::{Role, RolesError};
+use io::{
+    FTokenEvent, FeaturedReply, InitReply, InitThread, LogicAction, PostId, ReportReason,
+    ThreadType,
+};
+use logic_io::{InitLogic, ThreadLogic, ThreadLogicAction, ThreadLogicError, ThreadLogicEvent};
+use reward_logic_io::{
+    InitRewardLogic, RewardLogic, RewardLogicAction, RewardLogicError, RewardLogicEvent,
+    RewardPolicy, Settlement, SettlementOutcome,
+};
+use storage_io::{
+    InitStorage, StorageAction, StorageError, StorageEvent, StorageQuery, StorageQueryReply,
+};
+
+const ADMIN: u64 = 10;
+const ALICE: u64 = 11;
+const BOB: u64 = 12;
+const CAROL: u64 = 13;
+// Receives the commission of every settlement
+const TREASURY: u64 = 14;
+
+const FT_ID: u64 = 1;
+const STORAGE_ID: u64 = 2;
+const LOGIC_ID: u64 = 3;
+const REWARD_LOGIC_ID: u64 = 4;
+
+const INITIAL_BALANCE: u128 = 100;
+// Long enough for the scheduled expiry to fire only in tests spending the blocks
+const THREAD_DURATION: u32 = 1_000;
+
+struct Forum<'a> {
+    ft: Program<'a>,
+    storage: Program<'a>,
+    logic: Program<'a>,
+    reward_logic: Program<'a>,
+}
+
+fn wasm_binary(name: &str) -> Vec<u8> {
+    let profile = if cfg!(debug_assertions) {
+        "debug"
+    } else {
+        "release"
+    };
+    let path = format!("../target/wasm32-unknown-unknown/{profile}/{name}.opt.wasm");
+    std::fs::read(&path).unwrap_or_else(|_| panic!("Failed to read {path}"))
+}
+
+fn assert_reply(res: &RunResult, to: u64, reply: impl Encode) {
+    assert!(!res.main_failed());
+    assert!(res.contains(&(to, reply.encode())));
+}
+
+fn balance_of(ft: &Program, account: u64) -> u128 {
+    ft.read_state(ActorId::from(account))
+        .expect("Failed to read balance")
+}
+
+/// Deploys the FT mock, storage, logic and reward logic programs already wired to each
+/// other, and funds the users.
+fn deploy(sys: &System) -> Forum<'_> {
+    sys.init_logger();
+
+    let ft = Program::from_binary_with_id(sys, FT_ID, wasm_binary("ft_mock"));
+    let operators = vec![ActorId::from(LOGIC_ID), ActorId::from(REWARD_LOGIC_ID)];
+    assert!(!ft.send(ADMIN, operators).main_failed());
+
+    let storage = Program::from_binary_with_id(sys, STORAGE_ID, wasm_binary("storage"));
+    let init_storage = InitStorage {
+        owner: None,
+        address_logic_contract: Some(LOGIC_ID.into()),
+        address_reward_logic_contract: Some(REWARD_LOGIC_ID.into()),
+    };
+    assert!(!storage.send(ADMIN, init_storage).main_failed());
+
+    let logic = Program::from_binary_with_id(sys, LOGIC_ID, wasm_binary("logic"));
+    let init_logic = InitLogic {
+        owner: None,
+        address_ft: Some(FT_ID.into()),
+        address_storage: Some(STORAGE_ID.into()),
+        address_reward_logic: Some(REWARD_LOGIC_ID.into()),
+    };
+    assert!(!logic.send(ADMIN, init_logic).main_failed());
+
+    let reward_logic =
+        Program::from_binary_with_id(sys, REWARD_LOGIC_ID, wasm_binary("reward_logic"));
+    let init_reward_logic = InitRewardLogic {
+        owner: None,
+        address_ft: Some(FT_ID.into()),
+        address_logic: Some(LOGIC_ID.into()),
+        address_storage: Some(STORAGE_ID.into()),
+        address_treasury: Some(TREASURY.into()),
+        reward_policy: None,
+    };
+    assert!(!reward_logic.send(ADMIN, init_reward_logic).main_failed());
+
+    for user in [ALICE, BOB, CAROL] {
+        let res = ft.send(
+            ADMIN,
+            LogicAction::Mint {
+                recipient: user.into(),
+                amount: INITIAL_BALANCE,
+            },
+        );
+        assert_reply(&res, ADMIN, FTokenEvent::Ok);
+    }
+
+    Forum {
+        ft,
+        storage,
+        logic,
+        reward_logic,
+    }
+}
+
+fn new_thread(forum: &Forum, owner: u64, expected_id: PostId) {
+    let res = forum.logic.send(
+        owner,
+        ThreadLogicAction::NewThread(InitThread {
+            title: "Thread".into(),
+            content: "Content".into(),
+            photo_url: String::new(),
+            thread_type: ThreadType::Challenge,
+            duration: Some(THREAD_DURATION),
+        }),
+    );
+    assert_reply(
+        &res,
+        owner,
+        Ok::<_, ThreadLogicError>(ThreadLogicEvent::NewThreadCreated(expected_id)),
+    );
+}
+
+fn add_reply(forum: &Forum, owner: u64, thread_id: PostId, ref_node: PostId, expected_id: PostId) {
+    let init_reply = InitReply {
+        title: "Reply".into(),
+        content: "Content".into(),
+        photo_url: String::new(),
+    };
+    let res = forum.logic.send(
+        owner,
+        ThreadLogicAction::AddReply(thread_id, init_reply, ref_node),
+    );
+    assert_reply(
+        &res,
+        owner,
+        Ok::<_, ThreadLogicError>(ThreadLogicEvent::ReplyAdded(expected_id)),
+    );
+}
+
+fn like_reply(forum: &Forum, liker: u64, thread_id: PostId, reply_id: PostId, like_count: u128) {
+    let res = forum.logic.send(
+        liker,
+        ThreadLogicAction::LikeReply(thread_id, reply_id, like_count),
+    );
+    assert_reply(
+        &res,
+        liker,
+        Ok::<_, ThreadLogicError>(ThreadLogicEvent::ReplyLiked),
+    );
+}
+
+/// Alice opens thread 1, Bob and Carol reply to it with replies 2 and 3, and Alice and Carol
+/// like Bob's reply. The thread's reward pool ends up holding 10 tokens.
+fn populate(forum: &Forum) {
+    new_thread(forum, ALICE, 1);
+    add_reply(forum, BOB, 1, 1, 2);
+    add_reply(forum, CAROL, 1, 1, 3);
+    like_reply(forum, ALICE, 1, 2, 5);
+    like_reply(forum, CAROL, 1, 2, 2);
+}
+
+#[test]
+fn creates_thread_replies_and_likes() {
+    let sys = System::new();
+    let forum = deploy(&sys);
+
+    populate(&forum);
+
+    assert_eq!(balance_of(&forum.ft, ALICE), 95);
+    assert_eq!(balance_of(&forum.ft, BOB), 99);
+    assert_eq!(balance_of(&forum.ft, CAROL), 97);
+    assert_eq!(balance_of(&forum.ft, STORAGE_ID), 10);
+
+    let reply: Result<StorageQueryReply, StorageError> = forum
+        .storage
+        .read_state(StorageQuery::DistributedTokens(1))
+        .expect("Failed to read storage state");
+    assert!(matches!(
+        reply,
+        Ok(StorageQueryReply::DistributedTokens(10))
+    ));
+}
+
+#[test]
+fn schedules_thread_expiry() {
+    let sys = System::new();
+    let forum = deploy(&sys);
+
+    new_thread(&forum, ALICE, 1);
+
+    let thread_logic: ThreadLogic = forum
+        .logic
+        .read_state(())
+        .expect("Failed to read logic state");
+    assert_eq!(
+        thread_logic.thread_expiries.get(&1),
+        Some(&(sys.block_height() + THREAD_DURATION))
+    );
+}
+
+#[test]
+fn state_reads_leave_contracts_configured() {
+    let sys = System::new();
+    let forum = deploy(&sys);
+
+    new_thread(&forum, ALICE, 1);
+
+    // Each contract is read twice in a row and then handles a message that needs the state
+    // the reads went through
+    for _ in 0..2 {
+        let reply: Result<StorageQueryReply, StorageError> = forum
+            .storage
+            .read_state(StorageQuery::DistributedTokens(1))
+            .expect("Failed to read storage state");
+        assert!(matches!(reply, Ok(StorageQueryReply::DistributedTokens(1))));
+    }
+    add_reply(&forum, BOB, 1, 1, 2);
+
+    for _ in 0..2 {
+        let thread_logic: ThreadLogic = forum
+            .logic
+            .read_state(())
+            .expect("Failed to read logic state");
+        assert_eq!(
+            thread_logic.address_storage,
+            Some(ActorId::from(STORAGE_ID))
+        );
+        assert_eq!(
+            thread_logic.address_reward_logic,
+            Some(ActorId::from(REWARD_LOGIC_ID))
+        );
+    }
+    add_reply(&forum, CAROL, 1, 1, 3);
+    like_reply(&forum, ALICE, 1, 2, 5);
+    like_reply(&forum, CAROL, 1, 2, 2);
+
+    for _ in 0..2 {
+        let reward_logic: RewardLogic = forum
+            .reward_logic
+            .read_state(())
+            .expect("Failed to read reward logic state");
+        assert_eq!(reward_logic.address_logic, Some(ActorId::from(LOGIC_ID)));
+        assert_eq!(reward_logic.address_treasury, Some(ActorId::from(TREASURY)));
+    }
+
+    // Settling needs every address of the reward logic: the logic as caller, the storage,
+    // the token and the treasury
+    let res = forum.logic.send(ADMIN, ThreadLogicAction::ExpireThread(1));
+    assert_reply(
+        &res,
+        ADMIN,
+        Ok::<_, ThreadLogicError>(ThreadLogicEvent::ThreadExpired(
+            SettlementOutcome::Distributed,
+        )),
+    );
+    assert_eq!(balance_of(&forum.ft, TREASURY), 1);
+    assert_eq!(balance_of(&forum.ft, STORAGE_ID), 0);
+}
+
+#[test]
+fn setters_rewire_initialized_contracts() {
+    let sys = System::new();
+    let forum = deploy(&sys);
+
+    let res = forum
+        .storage
+        .send(ALICE, StorageAction::AddLogicContractAddress(ALICE.into()));
+    assert_reply(
+        &res,
+        ALICE,
+        Err::<StorageEvent, _>(StorageError::Roles(RolesError::MissingRole(Role::Admin))),
+    );
+
+    let res = forum.reward_logic.send(
+        ADMIN,
+        RewardLogicAction::AddAddressStorage(STORAGE_ID.into()),
+    );
+    assert_reply(
+        &res,
+        ADMIN,
+        Ok::<_, RewardLogicError>(RewardLogicEvent::StorageAddressAdded),
+    );
+
+    // Init payloads go through the same policy validation as the setters
+    let reward_logic = Program::from_binary_with_id(&sys, 5, wasm_binary("reward_logic"));
+    let init_reward_logic = InitRewardLogic {
+        reward_policy: Some(RewardPolicy {
+            winner_bps: 10_000,
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    assert!(reward_logic.send(ADMIN, init_reward_logic).main_failed());
+}
+
+#[test]
+fn settles_expired_thread() {
+    let sys = System::new();
+    let forum = deploy(&sys);
+
+    populate(&forum);
+
+    let res = forum.logic.send(ALICE, ThreadLogicAction::ExpireThread(1));
+    assert_reply(
+        &res,
+        ALICE,
+        Err::<ThreadLogicEvent, _>(ThreadLogicError::Roles(RolesError::MissingRole(
+            Role::Admin,
+        ))),
+    );
+
+    let res = forum.logic.send(ADMIN, ThreadLogicAction::ExpireThread(1));
+    assert_reply(
+        &res,
+        ADMIN,
+        Ok::<_, ThreadLogicError>(ThreadLogicEvent::ThreadExpired(
+            SettlementOutcome::Distributed,
+        )),
+    );
+
+    // Bob's reply wins 30%, the path Alice -> Bob splits 40%, Alice as top liker of
+    // the winner gets 20% and the treasury receives the 10% commission
+    assert_eq!(balance_of(&forum.ft, ALICE), 95 + 2 + 2);
+    assert_eq!(balance_of(&forum.ft, BOB), 99 + 3 + 2);
+    assert_eq!(balance_of(&forum.ft, CAROL), 97);
+    assert_eq!(balance_of(&forum.ft, TREASURY), 1);
+    assert_eq!(balance_of(&forum.ft, STORAGE_ID), 0);
+
+    // Expiring the thread again neither pays out nor changes the balances
+    let res = forum.logic.send(ADMIN, ThreadLogicAction::ExpireThread(1));
+    assert_reply(
+        &res,
+        ADMIN,
+        Err::<ThreadLogicEvent, _>(ThreadLogicError::ThreadExpired),
+    );
+    assert_eq!(balance_of(&forum.ft, STORAGE_ID), 0);
+}
+
+#[test]
+fn likes_are_rejected_while_expired_thread_awaits_settlement() {
+    let sys = System::new();
+    let forum = deploy(&sys);
+
+    populate(&forum);
+
+    // With settlement halted the expiry stops after expiring the thread in storage
+    let res = forum.reward_logic.send(
+        ADMIN,
+        RewardLogicAction::SetPause(PauseScope::Settlement, true),
+    );
+    assert!(!res.main_failed());
+    let res = forum.logic.send(ADMIN, ThreadLogicAction::ExpireThread(1));
+    assert_reply(
+        &res,
+        ADMIN,
+        Err::<ThreadLogicEvent, _>(ThreadLogicError::RewardLogic(RewardLogicError::Paused(
+            PauseScope::Settlement,
+        ))),
+    );
+
+    let res = forum
+        .logic
+        .send(CAROL, ThreadLogicAction::LikeReply(1, 2, 3));
+    assert_reply(
+        &res,
+        CAROL,
+        Err::<ThreadLogicEvent, _>(ThreadLogicError::ThreadExpired),
+    );
+    assert_eq!(balance_of(&forum.ft, CAROL), 97);
+    assert_eq!(balance_of(&forum.ft, STORAGE_ID), 10);
+
+    // Resuming settles the pool as it was at expiry
+    let res = forum.reward_logic.send(
+        ADMIN,
+        RewardLogicAction::SetPause(PauseScope::Settlement, false),
+    );
+    assert!(!res.main_failed());
+    let res = forum.logic.send(ADMIN, ThreadLogicAction::ExpireThread(1));
+    assert_reply(
+        &res,
+        ADMIN,
+        Ok::<_, ThreadLogicError>(ThreadLogicEvent::ThreadExpired(
+            SettlementOutcome::Distributed,
+        )),
+    );
+    assert_eq!(balance_of(&forum.ft, ALICE), 95 + 2 + 2);
+    assert_eq!(balance_of(&forum.ft, BOB), 99 + 3 + 2);
+    assert_eq!(balance_of(&forum.ft, STORAGE_ID), 0);
+}
+
+#[test]
+fn expires_thread_at_its_deadline() {
+    let sys = System::new();
+    let forum = deploy(&sys);
+
+    populate(&forum);
+
+    // Nobody expires the thread, its scheduled message settles it
+    let results = sys.spend_blocks(THREAD_DURATION);
+    assert!(results.iter().all(|res| !res.main_failed()));
+
+    assert_eq!(balance_of(&forum.ft, ALICE), 95 + 2 + 2);
+    assert_eq!(balance_of(&forum.ft, BOB), 99 + 3 + 2);
+    assert_eq!(balance_of(&forum.ft, TREASURY), 1);
+    assert_eq!(balance_of(&forum.ft, STORAGE_ID), 0);
+
+    let thread_logic: ThreadLogic = forum
+        .logic
+        .read_state(())
+        .expect("Failed to read logic state");
+    assert!(thread_logic.thread_expiries.is_empty());
+    assert!(thread_logic.scheduled_expiries.is_empty());
+}
+
+#[test]
+fn thread_past_its_deadline_takes_no_likes_while_settlement_is_paused() {
+    let sys = System::new();
+    let forum = deploy(&sys);
+
+    let res = forum.logic.send(
+        ADMIN,
+        ThreadLogicAction::SetPause(PauseScope::Settlement, true),
+    );
+    assert!(!res.main_failed());
+    populate(&forum);
+
+    // The scheduled expiry falls into the pause, the thread is still active in storage
+    sys.spend_blocks(THREAD_DURATION);
+    let thread_logic: ThreadLogic = forum
+        .logic
+        .read_state(())
+        .expect("Failed to read logic state");
+    assert!(thread_logic.manual_expiries.contains(&1));
+    assert!(thread_logic.scheduled_expiries.is_empty());
+
+    let res = forum
+        .logic
+        .send(CAROL, ThreadLogicAction::LikeReply(1, 2, 3));
+    assert_reply(
+        &res,
+        CAROL,
+        Err::<ThreadLogicEvent, _>(ThreadLogicError::ThreadExpired),
+    );
+    assert_eq!(balance_of(&forum.ft, CAROL), 97);
+    assert_eq!(balance_of(&forum.ft, STORAGE_ID), 10);
+
+    let res = forum.logic.send(
+        ADMIN,
+        ThreadLogicAction::SetPause(PauseScope::Settlement, false),
+    );
+    assert!(!res.main_failed());
+    let res = forum.logic.send(ADMIN, ThreadLogicAction::ExpireThread(1));
+    assert_reply(
+        &res,
+        ADMIN,
+        Ok::<_, ThreadLogicError>(ThreadLogicEvent::ThreadExpired(
+            SettlementOutcome::Distributed,
+        )),
+    );
+    assert_eq!(balance_of(&forum.ft, STORAGE_ID), 0);
+    let thread_logic: ThreadLogic = forum
+        .logic
+        .read_state(())
+        .expect("Failed to read logic state");
+    assert!(thread_logic.manual_expiries.is_empty());
+}
+
+#[test]
+fn failed_thread_creation_schedules_no_expiry() {
+    let sys = System::new();
+    let forum = deploy(&sys);
+
+    // Storage no longer accepts threads from the logic contract
+    let res = forum
+        .storage
+        .send(ADMIN, StorageAction::AddLogicContractAddress(ADMIN.into()));
+    assert!(!res.main_failed());
+
+    let res = forum.logic.send(
+        ALICE,
+        ThreadLogicAction::NewThread(InitThread {
+            title: "Thread".into(),
+            content: "Content".into(),
+            photo_url: String::new(),
+            thread_type: ThreadType::Challenge,
+            duration: Some(THREAD_DURATION),
+        }),
+    );
+    assert_reply(
+        &res,
+        ALICE,
+        Err::<ThreadLogicEvent, _>(ThreadLogicError::Storage(StorageError::Unauthorized)),
+    );
+
+    let thread_logic: ThreadLogic = forum
+        .logic
+        .read_state(())
+        .expect("Failed to read logic state");
+    assert_eq!(thread_logic.next_expiry_id, 0);
+    assert!(thread_logic.scheduled_expiries.is_empty());
+    assert!(thread_logic.thread_expiries.is_empty());
+    assert_eq!(balance_of(&forum.ft, STORAGE_ID), 0);
+}
+
+#[test]
+fn ignores_scheduled_expiry_of_thread_expired_early() {
+    let sys = System::new();
+    let forum = deploy(&sys);
+
+    new_thread(&forum, ALICE, 1);
+
+    let res = forum.logic.send(ADMIN, ThreadLogicAction::ExpireThread(1));
+    assert_reply(
+        &res,
+        ADMIN,
+        Ok::<_, ThreadLogicError>(ThreadLogicEvent::ThreadExpired(
+            SettlementOutcome::NoReplies,
+        )),
+    );
+
+    // The delayed message still arrives, and finds nothing left to expire
+    let results = sys.spend_blocks(THREAD_DURATION);
+    assert!(results.iter().all(|res| !res.main_failed()));
+
+    assert_eq!(balance_of(&forum.ft, TREASURY), 1);
+    assert_eq!(balance_of(&forum.ft, STORAGE_ID), 0);
+    let thread_logic: ThreadLogic = forum
+        .logic
+        .read_state(())
+        .expect("Failed to read logic state");
+    assert!(thread_logic.scheduled_expiries.is_empty());
+}
+
+#[test]
+fn active_threads_keep_their_pool_from_removal() {
+    let sys = System::new();
+    let forum = deploy(&sys);
+
+    populate(&forum);
+
+    let res = forum.storage.send(ADMIN, StorageAction::RemoveThread(1));
+    assert_reply(
+        &res,
+        ADMIN,
+        Err::<StorageEvent, _>(StorageError::ThreadActive),
+    );
+
+    let res = forum.logic.send(ADMIN, ThreadLogicAction::ExpireThread(1));
+    assert!(!res.main_failed());
+    let res = forum.storage.send(ADMIN, StorageAction::RemoveThread(1));
+    assert_reply(
+        &res,
+        ADMIN,
+        Ok::<_, StorageError>(StorageEvent::ThreadRemoved),
+    );
+    assert_eq!(balance_of(&forum.ft, STORAGE_ID), 0);
+}
+
+#[test]
+fn sends_minted_pool_of_thread_without_replies_to_treasury() {
+    let sys = System::new();
+    let forum = deploy(&sys);
+
+    new_thread(&forum, ALICE, 1);
+
+    let res = forum.logic.send(ADMIN, ThreadLogicAction::ExpireThread(1));
+    assert_reply(
+        &res,
+        ADMIN,
+        Ok::<_, ThreadLogicError>(ThreadLogicEvent::ThreadExpired(
+            SettlementOutcome::NoReplies,
+        )),
+    );
+
+    // The minted thread tokens are the whole pool, Alice deposited nothing to get back
+    assert_eq!(balance_of(&forum.ft, ALICE), INITIAL_BALANCE);
+    assert_eq!(balance_of(&forum.ft, TREASURY), 1);
+    assert_eq!(balance_of(&forum.ft, STORAGE_ID), 0);
+}
+
+#[test]
+fn settles_thread_without_likes() {
+    let sys = System::new();
+    let forum = deploy(&sys);
+
+    new_thread(&forum, ALICE, 1);
+    add_reply(&forum, BOB, 1, 1, 2);
+
+    let res = forum.logic.send(ADMIN, ThreadLogicAction::ExpireThread(1));
+    assert_reply(
+        &res,
+        ADMIN,
+        Ok::<_, ThreadLogicError>(ThreadLogicEvent::ThreadExpired(SettlementOutcome::NoLikers)),
+    );
+
+    // The 2 tokens pool rounds every share down to nothing, all of it is rounding dust
+    let reward_logic: RewardLogic = forum
+        .reward_logic
+        .read_state(())
+        .expect("Failed to read reward logic state");
+    assert_eq!(
+        reward_logic.settlements.get(&1),
+        Some(&Settlement::Settled(
+            SettlementOutcome::NoLikers,
+            vec![(TREASURY.into(), 2)]
+        ))
+    );
+    assert_eq!(balance_of(&forum.ft, STORAGE_ID), 0);
+}
+
+#[test]
+fn settles_thread_won_by_nested_reply() {
+    let sys = System::new();
+    let forum = deploy(&sys);
+
+    new_thread(&forum, ALICE, 1);
+    add_reply(&forum, BOB, 1, 1, 2);
+    add_reply(&forum, CAROL, 1, 2, 3);
+    like_reply(&forum, ALICE, 1, 3, 6);
+
+    let res = forum.logic.send(ADMIN, ThreadLogicAction::ExpireThread(1));
+    assert_reply(
+        &res,
+        ADMIN,
+        Ok::<_, ThreadLogicError>(ThreadLogicEvent::ThreadExpired(
+            SettlementOutcome::Distributed,
+        )),
+    );
+
+    // Of the 9 tokens Carol wins 2, the path Alice -> Bob -> Carol gets 1 each, Alice as top
+    // liker gets 1 and the treasury receives the 10% commission plus 2 tokens of rounding dust
+    assert_eq!(balance_of(&forum.ft, ALICE), 94 + 1 + 1);
+    assert_eq!(balance_of(&forum.ft, BOB), 99 + 1);
+    assert_eq!(balance_of(&forum.ft, CAROL), 99 + 2 + 1);
+    assert_eq!(balance_of(&forum.ft, TREASURY), 3);
+    assert_eq!(balance_of(&forum.ft, STORAGE_ID), 0);
+}
+
+#[test]
+fn settles_thread_after_removing_reply_on_winner_path() {
+    let sys = System::new();
+    let forum = deploy(&sys);
+
+    new_thread(&forum, ALICE, 1);
+    add_reply(&forum, BOB, 1, 1, 2);
+    add_reply(&forum, CAROL, 1, 2, 3);
+    like_reply(&forum, ALICE, 1, 3, 6);
+
+    // Carol's reply now answers the thread directly, the pool keeps Bob's deposit
+    let res = forum.storage.send(ADMIN, StorageAction::RemoveReply(1, 2));
+    assert_reply(
+        &res,
+        ADMIN,
+        Ok::<_, StorageError>(StorageEvent::ReplyRemoved),
+    );
+
+    let res = forum.logic.send(ADMIN, ThreadLogicAction::ExpireThread(1));
+    assert_reply(
+        &res,
+        ADMIN,
+        Ok::<_, ThreadLogicError>(ThreadLogicEvent::ThreadExpired(
+            SettlementOutcome::Distributed,
+        )),
+    );
+
+    // Of the 9 tokens Carol wins 2, the path Alice -> Carol gets 1 each, Alice as top liker
+    // gets 1 and the treasury receives the commission with the rounding dust
+    assert_eq!(balance_of(&forum.ft, ALICE), 94 + 1 + 1);
+    assert_eq!(balance_of(&forum.ft, BOB), 99);
+    assert_eq!(balance_of(&forum.ft, CAROL), 99 + 2 + 1);
+    assert_eq!(balance_of(&forum.ft, TREASURY), 4);
+    assert_eq!(balance_of(&forum.ft, STORAGE_ID), 0);
+}
+
+#[test]
+fn failed_settlement_is_resolved_by_admin() {
+    let sys = System::new();
+    let forum = deploy(&sys);
+
+    populate(&forum);
+
+    // The admin operates the FT mock, and takes enough of the pool for Bob's 3 tokens to be
+    // the only payout going through
+    let res = forum.ft.send(
+        ADMIN,
+        LogicAction::Transfer {
+            sender: STORAGE_ID.into(),
+            recipient: ADMIN.into(),
+            amount: 6,
+        },
+    );
+    assert_reply(&res, ADMIN, FTokenEvent::Ok);
+
+    let res = forum.logic.send(ADMIN, ThreadLogicAction::ExpireThread(1));
+    assert_reply(
+        &res,
+        ADMIN,
+        Err::<ThreadLogicEvent, _>(ThreadLogicError::RewardLogic(
+            RewardLogicError::FtTransferFailed,
+        )),
+    );
+    let reward_logic: RewardLogic = forum
+        .reward_logic
+        .read_state(())
+        .expect("Failed to read reward logic state");
+    assert_eq!(
+        reward_logic.settlements.get(&1),
+        Some(&Settlement::Failed(vec![(BOB.into(), 3)]))
+    );
+
+    let res = forum.ft.send(
+        ADMIN,
+        LogicAction::Transfer {
+            sender: ADMIN.into(),
+            recipient: STORAGE_ID.into(),
+            amount: 6,
+        },
+    );
+    assert_reply(&res, ADMIN, FTokenEvent::Ok);
+
+    let res = forum
+        .reward_logic
+        .send(BOB, RewardLogicAction::ResolveSettlement(1));
+    assert_reply(
+        &res,
+        BOB,
+        Err::<RewardLogicEvent, _>(RewardLogicError::Roles(RolesError::MissingRole(
+            Role::Admin,
+        ))),
+    );
+    let res = forum
+        .reward_logic
+        .send(ADMIN, RewardLogicAction::ResolveSettlement(1));
+    assert_reply(
+        &res,
+        ADMIN,
+        Ok::<_, RewardLogicError>(RewardLogicEvent::SettlementResolved(
+            SettlementOutcome::Distributed,
+        )),
+    );
+
+    // Bob is not paid twice, everybody else gets their share
+    assert_eq!(balance_of(&forum.ft, ALICE), 95 + 2 + 2);
+    assert_eq!(balance_of(&forum.ft, BOB), 99 + 3 + 2);
+    assert_eq!(balance_of(&forum.ft, TREASURY), 1);
+    assert_eq!(balance_of(&forum.ft, STORAGE_ID), 0);
+
+    // The logic contract completes the expiry it still has pending
+    let res = forum.logic.send(ADMIN, ThreadLogicAction::ExpireThread(1));
+    assert_reply(
+        &res,
+        ADMIN,
+        Ok::<_, ThreadLogicError>(ThreadLogicEvent::ThreadExpired(
+            SettlementOutcome::Distributed,
+        )),
+    );
+    let thread_logic: ThreadLogic = forum
+        .logic
+        .read_state(())
+        .expect("Failed to read logic state");
+    assert!(thread_logic.thread_expiries.is_empty());
+}
+
+#[test]
+fn refunds_reply_to_unknown_post() {
+    let sys = System::new();
+    let forum = deploy(&sys);
+
+    new_thread(&forum, ALICE, 1);
+
+    let init_reply = InitReply {
+        title: "Reply".into(),
+        content: "Content".into(),
+        photo_url: String::new(),
+    };
+    let res = forum
+        .logic
+        .send(BOB, ThreadLogicAction::AddReply(1, init_reply, 42));
+    assert_reply(
+        &res,
+        BOB,
+        Err::<ThreadLogicEvent, _>(ThreadLogicError::Storage(StorageError::RefNodeNotFound)),
+    );
+
+    assert_eq!(balance_of(&forum.ft, BOB), INITIAL_BALANCE);
+    assert_eq!(balance_of(&forum.ft, STORAGE_ID), 1);
+}
+
+#[test]
+fn refunds_like_when_storage_rejects_it() {
+    let sys = System::new();
+    let forum = deploy(&sys);
+
+    new_thread(&forum, ALICE, 1);
+
+    // Reply 99 does not exist, storage rejects the like after the tokens were transferred
+    let res = forum
+        .logic
+        .send(BOB, ThreadLogicAction::LikeReply(1, 99, 5));
+    assert_reply(
+        &res,
+        BOB,
+        Err::<ThreadLogicEvent, _>(ThreadLogicError::Storage(StorageError::ReplyNotFound)),
+    );
+
+    assert_eq!(balance_of(&forum.ft, BOB), INITIAL_BALANCE);
+    assert_eq!(balance_of(&forum.ft, STORAGE_ID), 1);
+}
+
+#[test]
+fn refunded_deposits_leave_nothing_to_claim() {
+    let sys = System::new();
+    let forum = deploy(&sys);
+
+    new_thread(&forum, ALICE, 1);
+    let res = forum
+        .logic
+        .send(BOB, ThreadLogicAction::LikeReply(1, 99, 5));
+    assert!(!res.main_failed());
+
+    // The deposit went straight back, no refund is left pending
+    let thread_logic: ThreadLogic = forum
+        .logic
+        .read_state(())
+        .expect("Failed to read logic state");
+    assert!(thread_logic.pending_refunds.is_empty());
+
+    let res = forum.logic.send(BOB, ThreadLogicAction::ClaimRefund);
+    assert_reply(
+        &res,
+        BOB,
+        Err::<ThreadLogicEvent, _>(ThreadLogicError::NothingToRefund),
+    );
+    assert_eq!(balance_of(&forum.ft, BOB), INITIAL_BALANCE);
+}
+
+#[test]
+fn likes_without_tokens_are_rejected() {
+    let sys = System::new();
+    let forum = deploy(&sys);
+
+    populate(&forum);
+
+    let res = forum.logic.send(BOB, ThreadLogicAction::LikeReply(1, 3, 0));
+    assert_reply(
+        &res,
+        BOB,
+        Err::<ThreadLogicEvent, _>(ThreadLogicError::InvalidLikeCount),
+    );
+    assert_eq!(balance_of(&forum.ft, STORAGE_ID), 10);
+
+    let deposits: Result<StorageQueryReply, StorageError> = forum
+        .storage
+        .read_state(StorageQuery::DepositLedger(1))
+        .expect("Failed to read storage state");
+    let Ok(StorageQueryReply::DepositLedger(deposits)) = deposits else {
+        panic!("Unexpected storage reply");
+    };
+    assert_eq!(deposits.len(), 5);
+}
+
+#[test]
+fn global_pause_stops_refund_claims() {
+    let sys = System::new();
+    let forum = deploy(&sys);
+
+    let res = forum
+        .logic
+        .send(ADMIN, ThreadLogicAction::SetPause(PauseScope::All, true));
+    assert!(!res.main_failed());
+
+    let res = forum.logic.send(BOB, ThreadLogicAction::ClaimRefund);
+    assert_reply(
+        &res,
+        BOB,
+        Err::<ThreadLogicEvent, _>(ThreadLogicError::Paused(PauseScope::All)),
+    );
+}
+
+#[test]
+fn reward_logic_pauses_only_settlement() {
+    let sys = System::new();
+    let forum = deploy(&sys);
+
+    for scope in [PauseScope::Posting, PauseScope::Liking] {
+        let res = forum
+            .reward_logic
+            .send(ADMIN, RewardLogicAction::SetPause(scope, true));
+        assert_reply(
+            &res,
+            ADMIN,
+            Err::<RewardLogicEvent, _>(RewardLogicError::UnsupportedPauseScope(scope)),
+        );
+    }
+
+    let res = forum.reward_logic.send(
+        ADMIN,
+        RewardLogicAction::SetPause(PauseScope::Settlement, true),
+    );
+    assert_reply(
+        &res,
+        ADMIN,
+        Ok::<_, RewardLogicError>(RewardLogicEvent::PauseSet),
+    );
+    let reward_logic: RewardLogic = forum
+        .reward_logic
+        .read_state(())
+        .expect("Failed to read reward logic state");
+    assert_eq!(
+        reward_logic.pause,
+        PauseFlags {
+            settlement: true,
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn rejected_ft_transfer_keeps_the_allowance() {
+    let sys = System::new();
+    let forum = deploy(&sys);
+
+    let res = forum.ft.send(
+        CAROL,
+        LogicAction::Approve {
+            approved_account: BOB.into(),
+            amount: 2 * INITIAL_BALANCE,
+        },
+    );
+    assert_reply(&res, CAROL, FTokenEvent::Ok);
+
+    // Carol does not hold enough tokens, the allowance stays untouched
+    let transfer = |amount| LogicAction::Transfer {
+        sender: CAROL.into(),
+        recipient: BOB.into(),
+        amount,
+    };
+    let res = forum.ft.send(BOB, transfer(2 * INITIAL_BALANCE));
+    assert_reply(&res, BOB, FTokenEvent::Err);
+    let res = forum.ft.send(BOB, transfer(INITIAL_BALANCE));
+    assert_reply(&res, BOB, FTokenEvent::Ok);
+
+    assert_eq!(balance_of(&forum.ft, CAROL), 0);
+    assert_eq!(balance_of(&forum.ft, BOB), 2 * INITIAL_BALANCE);
+}
+
+#[test]
+fn rejects_storage_mutations_from_other_senders() {
+    let sys = System::new();
+    let forum = deploy(&sys);
+
+    new_thread(&forum, ALICE, 1);
+
+    let res = forum.storage.send(
+        BOB,
+        StorageAction::LikeReply(1, 1, ActorId::from(BOB), 1_000),
+    );
+    assert_reply(
+        &res,
+        BOB,
+        Err::<StorageEvent, _>(StorageError::Unauthorized),
+    );
+
+    let res = forum
+        .reward_logic
+        .send(BOB, RewardLogicAction::TriggerRewardLogic(1));
+    assert_reply(
+        &res,
+        BOB,
+        Err::<RewardLogicEvent, _>(RewardLogicError::Unauthorized),
+    );
+}
+
+#[test]
+fn paused_likes_are_rejected_before_taking_tokens() {
+    let sys = System::new();
+    let forum = deploy(&sys);
+
+    new_thread(&forum, ALICE, 1);
+    add_reply(&forum, BOB, 1, 1, 2);
+
+    let res = forum
+        .logic
+        .send(ALICE, ThreadLogicAction::SetPause(PauseScope::Liking, true));
+    assert_reply(
+        &res,
+        ALICE,
+        Err::<ThreadLogicEvent, _>(ThreadLogicError::Roles(RolesError::MissingRole(
+            Role::Admin,
+        ))),
+    );
+
+    let res = forum
+        .logic
+        .send(ADMIN, ThreadLogicAction::SetPause(PauseScope::Liking, true));
+    assert_reply(
+        &res,
+        ADMIN,
+        Ok::<_, ThreadLogicError>(ThreadLogicEvent::PauseSet),
+    );
+
+    let res = forum
+        .logic
+        .send(CAROL, ThreadLogicAction::LikeReply(1, 2, 5));
+    assert_reply(
+        &res,
+        CAROL,
+        Err::<ThreadLogicEvent, _>(ThreadLogicError::Paused(PauseScope::Liking)),
+    );
+    assert_eq!(balance_of(&forum.ft, CAROL), INITIAL_BALANCE);
+
+    // Posting is not affected by a liking pause
+    add_reply(&forum, CAROL, 1, 1, 3);
+
+    let thread_logic: ThreadLogic = forum
+        .logic
+        .read_state(())
+        .expect("Failed to read logic state");
+    assert_eq!(
+        thread_logic.pause,
+        PauseFlags {
+            liking: true,
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn rejects_reward_policy_not_covering_whole_pool() {
+    let sys = System::new();
+    let forum = deploy(&sys);
+
+    let reward_policy = RewardPolicy {
+        winner_bps: 5_000,
+        top_liker_bps: 2_000,
+        path_bps: 4_000,
+        commission_bps: 1_000,
+    };
+    let res = forum
+        .reward_logic
+        .send(ADMIN, RewardLogicAction::SetRewardPolicy(reward_policy));
+    assert_reply(
+        &res,
+        ADMIN,
+        Err::<RewardLogicEvent, _>(RewardLogicError::InvalidRewardPolicy),
+    );
+
+    let res = forum.reward_logic.send(
+        ADMIN,
+        RewardLogicAction::SetThreadTypeRewardPolicy(
+            ThreadType::Question,
+            Some(RewardPolicy {
+                winner_bps: 4_000,
+                ..reward_policy
+            }),
+        ),
+    );
+    assert_reply(
+        &res,
+        ADMIN,
+        Ok::<_, RewardLogicError>(RewardLogicEvent::ThreadTypeRewardPolicySet),
+    );
+}
+
+#[test]
+fn reward_policy_is_managed_by_treasurers() {
+    let sys = System::new();
+    let forum = deploy(&sys);
+
+    let res = forum.reward_logic.send(
+        ALICE,
+        RewardLogicAction::SetRewardPolicy(RewardPolicy::default()),
+    );
+    assert_reply(
+        &res,
+        ALICE,
+        Err::<RewardLogicEvent, _>(RewardLogicError::Roles(RolesError::MissingRole(
+            Role::Treasurer,
+        ))),
+    );
+
+    // Only the owner hands out the treasurer role
+    let res = forum.reward_logic.send(
+        BOB,
+        RewardLogicAction::GrantRole(Role::Treasurer, ActorId::from(ALICE)),
+    );
+    assert_reply(
+        &res,
+        BOB,
+        Err::<RewardLogicEvent, _>(RewardLogicError::Roles(RolesError::MissingRole(
+            Role::Owner,
+        ))),
+    );
+
+    let res = forum.reward_logic.send(
+        ADMIN,
+        RewardLogicAction::GrantRole(Role::Treasurer, ActorId::from(ALICE)),
+    );
+    assert_reply(
+        &res,
+        ADMIN,
+        Ok::<_, RewardLogicError>(RewardLogicEvent::RoleGranted),
+    );
+
+    let res = forum.reward_logic.send(
+        ALICE,
+        RewardLogicAction::SetRewardPolicy(RewardPolicy::default()),
+    );
+    assert_reply(
+        &res,
+        ALICE,
+        Ok::<_, RewardLogicError>(RewardLogicEvent::RewardPolicySet),
+    );
+}
+
+#[test]
+fn commission_goes_to_treasury_set_by_treasurers() {
+    let sys = System::new();
+    let forum = deploy(&sys);
+
+    let res = forum
+        .reward_logic
+        .send(BOB, RewardLogicAction::SetTreasuryAddress(BOB.into()));
+    assert_reply(
+        &res,
+        BOB,
+        Err::<RewardLogicEvent, _>(RewardLogicError::Roles(RolesError::MissingRole(
+            Role::Treasurer,
+        ))),
+    );
+    let res = forum
+        .reward_logic
+        .send(ADMIN, RewardLogicAction::SetTreasuryAddress(CAROL.into()));
+    assert_reply(
+        &res,
+        ADMIN,
+        Ok::<_, RewardLogicError>(RewardLogicEvent::TreasuryAddressSet),
+    );
+
+    populate(&forum);
+    let res = forum.logic.send(ADMIN, ThreadLogicAction::ExpireThread(1));
+    assert!(!res.main_failed());
+
+    assert_eq!(balance_of(&forum.ft, CAROL), 97 + 1);
+    assert_eq!(balance_of(&forum.ft, TREASURY), 0);
+    assert_eq!(balance_of(&forum.ft, ADMIN), 0);
+}
+
+#[test]
+fn thread_owner_selects_featured_reply() {
+    let sys = System::new();
+    let forum = deploy(&sys);
+
+    populate(&forum);
+
+    let res = forum.logic.send(
+        BOB,
+        ThreadLogicAction::SetFeaturedReply(1, FeaturedReply::Pinned(2)),
+    );
+    assert_reply(
+        &res,
+        BOB,
+        Err::<ThreadLogicEvent, _>(ThreadLogicError::NotThreadOwner),
+    );
+
+    let res = forum.logic.send(
+        ALICE,
+        ThreadLogicAction::SetFeaturedReply(1, FeaturedReply::Accepted(3)),
+    );
+    assert_reply(
+        &res,
+        ALICE,
+        Err::<ThreadLogicEvent, _>(ThreadLogicError::Storage(StorageError::NotAQuestion)),
+    );
+
+    let res = forum.logic.send(
+        ALICE,
+        ThreadLogicAction::SetFeaturedReply(1, FeaturedReply::Pinned(3)),
+    );
+    assert_reply(
+        &res,
+        ALICE,
+        Ok::<_, ThreadLogicError>(ThreadLogicEvent::FeaturedReplySet),
+    );
+
+    // Carol's reply is featured even though Bob's has more likes
+    let reply: Result<StorageQueryReply, StorageError> = forum
+        .storage
+        .read_state(StorageQuery::AllThreadsFE(Default::default()))
+        .expect("Failed to read storage state");
+    let Ok(StorageQueryReply::AllThreadsFE(threads)) = reply else {
+        panic!("Unexpected storage reply");
+    };
+    let (thread, featured_reply) = &threads.items[0];
+    assert_eq!(thread.featured_reply, FeaturedReply::Pinned(3));
+    assert_eq!(featured_reply.as_ref().map(|post| post.post_id), Some(3));
+}
+
+#[test]
+fn reports_are_filed_once_per_actor() {
+    let sys = System::new();
+    let forum = deploy(&sys);
+
+    populate(&forum);
+
+    let res = forum.logic.send(
+        BOB,
+        ThreadLogicAction::ReportReply(1, 3, ReportReason::OffTopic),
+    );
+    assert_reply(
+        &res,
+        BOB,
+        Ok::<_, ThreadLogicError>(ThreadLogicEvent::PostReported(false)),
+    );
+
+    let res = forum.logic.send(
+        BOB,
+        ThreadLogicAction::ReportReply(1, 3, ReportReason::Spam),
+    );
+    assert_reply(
+        &res,
+        BOB,
+        Err::<ThreadLogicEvent, _>(ThreadLogicError::Storage(StorageError::AlreadyReported)),
+    );
+}
+
+#[test]
+fn admins_moderate_with_a_threshold_of_at_least_one_report() {
+    let sys = System::new();
+    let forum = deploy(&sys);
+
+    populate(&forum);
+
+    let res = forum
+        .storage
+        .send(ADMIN, StorageAction::SetReportThreshold(0));
+    assert_reply(
+        &res,
+        ADMIN,
+        Err::<StorageEvent, _>(StorageError::InvalidReportThreshold),
+    );
+    let res = forum
+        .storage
+        .send(ADMIN, StorageAction::SetReportThreshold(1));
+    assert_reply(
+        &res,
+        ADMIN,
+        Ok::<_, StorageError>(StorageEvent::ReportThresholdSet),
+    );
+
+    let res = forum.logic.send(
+        BOB,
+        ThreadLogicAction::ReportReply(1, 3, ReportReason::Spam),
+    );
+    assert_reply(
+        &res,
+        BOB,
+        Ok::<_, ThreadLogicError>(ThreadLogicEvent::PostReported(true)),
+    );
+
+    // Admins act as moderators without holding the moderator role
+    let res = forum
+        .storage
+        .send(ADMIN, StorageAction::GrantRole(Role::Admin, CAROL.into()));
+    assert_reply(
+        &res,
+        ADMIN,
+        Ok::<_, StorageError>(StorageEvent::RoleGranted),
+    );
+    let res = forum.storage.send(CAROL, StorageAction::DismissReports(3));
+    assert_reply(
+        &res,
+        CAROL,
+        Ok::<_, StorageError>(StorageEvent::ReportsDismissed),
+    );
+}
+
+#[test]
+fn moderators_remove_reported_threads_once_expired() {
+    let sys = System::new();
+    let forum = deploy(&sys);
+
+    populate(&forum);
+
+    let res = forum
+        .storage
+        .send(ADMIN, StorageAction::SetReportThreshold(1));
+    assert!(!res.main_failed());
+    let res = forum
+        .logic
+        .send(BOB, ThreadLogicAction::ReportThread(1, ReportReason::Spam));
+    assert_reply(
+        &res,
+        BOB,
+        Ok::<_, ThreadLogicError>(ThreadLogicEvent::PostReported(true)),
+    );
+    let res = forum.storage.send(
+        ADMIN,
+        StorageAction::GrantRole(Role::Moderator, CAROL.into()),
+    );
+    assert!(!res.main_failed());
+
+    // The active thread stays hidden in the moderation queue
+    let res = forum.storage.send(CAROL, StorageAction::RemoveThread(1));
+    assert_reply(
+        &res,
+        CAROL,
+        Err::<StorageEvent, _>(StorageError::ThreadActive),
+    );
+    let queue: Result<StorageQueryReply, StorageError> = forum
+        .storage
+        .read_state(StorageQuery::ModerationQueue(None, 10))
+        .expect("Failed to read storage state");
+    let Ok(StorageQueryReply::ModerationQueue(queue)) = queue else {
+        panic!("Unexpected storage reply");
+    };
+    assert_eq!(queue.items.len(), 1);
+    assert_eq!(queue.items[0].post_id, 1);
+
+    let res = forum.logic.send(ADMIN, ThreadLogicAction::ExpireThread(1));
+    assert!(!res.main_failed());
+    let res = forum.storage.send(CAROL, StorageAction::RemoveThread(1));
+    assert_reply(
+        &res,
+        CAROL,
+        Ok::<_, StorageError>(StorageEvent::ThreadRemoved),
+    );
+}
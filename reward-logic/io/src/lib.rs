@@ -1,71 +1,242 @@
 #![no_std]
 
-use gmeta::{InOut, Metadata, Out};
-use gstd::{collections::HashMap as GHashMap, msg, prelude::*, ActorId};
+use gmeta::{In, InOut, Metadata, Out};
+use gstd::{collections::BTreeMap, msg, prelude::*, ActorId};
+use io::pause::{PauseFlags, PauseScope};
+use io::roles::{Role, Roles, RolesError};
 use io::{FTokenEvent, LogicAction};
-use io::{PostId, ThreadGraph, ThreadNode};
-use storage_io::{StorageQuery, StorageQueryReply};
+use io::{PostId, ThreadGraph, ThreadNode, ThreadType};
+use storage_io::{
+    Deposit, DepositSource, QueryThread, StorageAction, StorageError, StorageQuery,
+    StorageQueryReply,
+};
+
+// Shares of a reward policy are expressed in basis points, 10_000 being the whole pool
+pub const BASIS_POINTS: u16 = 10_000;
+
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, PartialEq, Eq)]
+#[codec(crate = gstd::codec)]
+#[scale_info(crate = gstd::scale_info)]
+pub enum Settlement {
+    // Rewards of the thread are being distributed, the given payouts are already made
+    InProgress(Vec<(ActorId, u128)>),
+    // Rewards have been distributed with the given payouts
+    Settled(SettlementOutcome, Vec<(ActorId, u128)>),
+    // Distribution stopped after the given payouts and is resumed with `ResolveSettlement`
+    Failed(Vec<(ActorId, u128)>),
+}
+
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, Copy, PartialEq, Eq)]
+#[codec(crate = gstd::codec)]
+#[scale_info(crate = gstd::scale_info)]
+pub enum SettlementOutcome {
+    // The pool was split between the winner reply, its top liker, the path and the commission
+    Distributed,
+    // Nobody replied to the thread, the owner got back what they deposited themselves and the
+    // treasury the rest of the pool
+    NoReplies,
+    // The winner reply has no likers, the top liker share went to the winner reply
+    NoLikers,
+}
+
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, Copy, PartialEq, Eq)]
+#[codec(crate = gstd::codec)]
+#[scale_info(crate = gstd::scale_info)]
+pub struct RewardPolicy {
+    pub winner_bps: u16,
+    pub top_liker_bps: u16,
+    pub path_bps: u16,
+    pub commission_bps: u16,
+}
+
+impl RewardPolicy {
+    /// Checks that the shares of the policy add up to the whole reward pool.
+    pub fn validate(&self) -> Result<(), RewardLogicError> {
+        let total = self.winner_bps as u32
+            + self.top_liker_bps as u32
+            + self.path_bps as u32
+            + self.commission_bps as u32;
+
+        if total != BASIS_POINTS as u32 {
+            return Err(RewardLogicError::InvalidRewardPolicy);
+        }
+        Ok(())
+    }
+
+    pub fn winner_share(&self, distributed_tokens: u128) -> u128 {
+        Self::share(self.winner_bps, distributed_tokens)
+    }
+
+    pub fn top_liker_share(&self, distributed_tokens: u128) -> u128 {
+        Self::share(self.top_liker_bps, distributed_tokens)
+    }
+
+    pub fn path_share(&self, distributed_tokens: u128) -> u128 {
+        Self::share(self.path_bps, distributed_tokens)
+    }
+
+    pub fn commission_share(&self, distributed_tokens: u128) -> u128 {
+        Self::share(self.commission_bps, distributed_tokens)
+    }
+
+    fn share(bps: u16, distributed_tokens: u128) -> u128 {
+        (distributed_tokens * bps as u128) / BASIS_POINTS as u128
+    }
+}
+
+impl Default for RewardPolicy {
+    /// 30% to the winner reply, 20% to its top liker, 40% to the path to the winner
+    /// and 10% Serenium commission.
+    fn default() -> Self {
+        RewardPolicy {
+            winner_bps: 3_000,
+            top_liker_bps: 2_000,
+            path_bps: 4_000,
+            commission_bps: 1_000,
+        }
+    }
+}
 
 #[derive(Default, Encode, Decode, TypeInfo)]
 #[codec(crate = gstd::codec)]
 #[scale_info(crate = gstd::scale_info)]
 pub struct RewardLogic {
-    pub admin: Option<ActorId>,
+    pub roles: Roles,
+    pub pause: PauseFlags,
     pub address_ft: Option<ActorId>,
     pub address_logic: Option<ActorId>,
     pub address_storage: Option<ActorId>,
+    // Receives the Serenium commission of every settlement
+    pub address_treasury: Option<ActorId>,
+    // Threads the reward logic has been triggered for, so rewards are distributed only once
+    pub settlements: BTreeMap<PostId, Settlement>,
+    pub reward_policy: RewardPolicy,
+    // Policies replacing `reward_policy` for threads of a given type
+    pub thread_type_policies: BTreeMap<ThreadType, RewardPolicy>,
 }
 
 impl RewardLogic {
     pub fn new() -> Self {
         RewardLogic {
-            admin: None,
+            roles: Roles::default(),
+            pause: PauseFlags::default(),
             address_ft: None,
             address_logic: None,
             address_storage: None,
+            address_treasury: None,
+            settlements: BTreeMap::new(),
+            reward_policy: RewardPolicy::default(),
+            thread_type_policies: BTreeMap::new(),
         }
     }
 
-    pub async fn fetch_all_replies_with_likes(
+    pub fn ensure_role(&self, caller: ActorId, role: Role) -> Result<(), RewardLogicError> {
+        self.roles
+            .ensure_role(caller, role)
+            .map_err(RewardLogicError::Roles)
+    }
+
+    pub fn ensure_not_paused(&self, scope: PauseScope) -> Result<(), RewardLogicError> {
+        if self.pause.is_paused(scope) {
+            return Err(RewardLogicError::Paused(scope));
+        }
+        Ok(())
+    }
+
+    /// Halts or resumes the actions of `scope`. Only settlement runs in this contract, so
+    /// posting and liking cannot be paused here.
+    pub fn set_pause(&mut self, scope: PauseScope, paused: bool) -> Result<(), RewardLogicError> {
+        match scope {
+            PauseScope::All | PauseScope::Settlement => {
+                self.pause.set(scope, paused);
+                Ok(())
+            }
+            PauseScope::Posting | PauseScope::Liking => {
+                Err(RewardLogicError::UnsupportedPauseScope(scope))
+            }
+        }
+    }
+
+    pub fn set_reward_policy(
         &mut self,
-        thread_id: PostId,
-    ) -> Option<Vec<(PostId, ActorId, u128)>> {
-        let res = msg::send_for_reply_as::<_, StorageQueryReply>(
-            self.address_storage.expect(""),
-            StorageQuery::AllRepliesWithLikes(thread_id),
-            0,
-            0,
-        )
-        .expect("")
-        .await;
+        reward_policy: RewardPolicy,
+    ) -> Result<(), RewardLogicError> {
+        reward_policy.validate()?;
+        self.reward_policy = reward_policy;
+        Ok(())
+    }
 
-        match res {
-            Ok(event) => match event {
-                StorageQueryReply::AllRepliesWithLikes(all_replies_with_likes) => {
-                    Some(all_replies_with_likes)
-                }
-                _ => None,
-            },
-            Err(_) => None,
+    /// Sets the policy used for threads of the given type, or removes the override
+    /// when `reward_policy` is `None`.
+    pub fn set_thread_type_policy(
+        &mut self,
+        thread_type: ThreadType,
+        reward_policy: Option<RewardPolicy>,
+    ) -> Result<(), RewardLogicError> {
+        match reward_policy {
+            Some(reward_policy) => {
+                reward_policy.validate()?;
+                self.thread_type_policies.insert(thread_type, reward_policy);
+            }
+            None => {
+                self.thread_type_policies.remove(&thread_type);
+            }
         }
+        Ok(())
+    }
+
+    pub fn policy_for(&self, thread_type: ThreadType) -> RewardPolicy {
+        self.thread_type_policies
+            .get(&thread_type)
+            .copied()
+            .unwrap_or(self.reward_policy)
     }
 
-    pub async fn fetch_graph_rep(&mut self, thread_id: PostId) -> Option<ThreadGraph> {
-        let res = msg::send_for_reply_as::<_, StorageQueryReply>(
-            self.address_storage.expect(""),
-            StorageQuery::GraphRep(thread_id),
+    async fn query_storage(
+        &self,
+        query: StorageQuery,
+    ) -> Result<StorageQueryReply, RewardLogicError> {
+        let address_storage = self
+            .address_storage
+            .ok_or(RewardLogicError::AddressNotConfigured)?;
+
+        msg::send_for_reply_as::<_, Result<StorageQueryReply, StorageError>>(
+            address_storage,
+            StorageAction::Query(query),
             0,
             0,
         )
-        .expect("")
-        .await;
+        .map_err(|_| RewardLogicError::MessageFailed)?
+        .await
+        .map_err(|_| RewardLogicError::MessageFailed)?
+        .map_err(RewardLogicError::Storage)
+    }
 
-        match res {
-            Ok(event) => match event {
-                StorageQueryReply::GraphRep(graph_rep) => Some(graph_rep),
-                _ => None,
-            },
-            Err(_) => None,
+    pub async fn fetch_all_replies_with_likes(
+        &mut self,
+        thread_id: PostId,
+    ) -> Result<Vec<(PostId, ActorId, u128)>, RewardLogicError> {
+        match self
+            .query_storage(StorageQuery::AllRepliesWithLikes(thread_id))
+            .await?
+        {
+            StorageQueryReply::AllRepliesWithLikes(all_replies_with_likes) => {
+                Ok(all_replies_with_likes)
+            }
+            _ => Err(RewardLogicError::UnexpectedReply),
+        }
+    }
+
+    pub async fn fetch_graph_rep(
+        &mut self,
+        thread_id: PostId,
+    ) -> Result<ThreadGraph, RewardLogicError> {
+        match self
+            .query_storage(StorageQuery::GraphRep(thread_id))
+            .await?
+        {
+            StorageQueryReply::GraphRep(graph_rep) => Ok(graph_rep),
+            _ => Err(RewardLogicError::UnexpectedReply),
         }
     }
 
@@ -73,53 +244,141 @@ impl RewardLogic {
         &mut self,
         thread_id: PostId,
         reply_id: PostId,
-    ) -> Option<Vec<(ActorId, u128)>> {
-        let res = msg::send_for_reply_as::<_, StorageQueryReply>(
-            self.address_storage.expect(""),
-            StorageQuery::LikeHistoryOf(thread_id, reply_id),
-            0,
-            0,
-        )
-        .expect("")
-        .await;
+    ) -> Result<Vec<(ActorId, u128)>, RewardLogicError> {
+        match self
+            .query_storage(StorageQuery::LikeHistoryOf(thread_id, reply_id))
+            .await?
+        {
+            StorageQueryReply::LikeHistoryOf(like_history) => Ok(like_history),
+            _ => Err(RewardLogicError::UnexpectedReply),
+        }
+    }
 
-        match res {
-            Ok(event) => match event {
-                StorageQueryReply::LikeHistoryOf(like_history) => Some(like_history),
-                _ => None,
-            },
-            Err(_) => None,
+    pub async fn fetch_thread(
+        &mut self,
+        thread_id: PostId,
+    ) -> Result<QueryThread, RewardLogicError> {
+        match self.query_storage(StorageQuery::Thread(thread_id)).await? {
+            StorageQueryReply::Thread(thread) => Ok(thread),
+            _ => Err(RewardLogicError::UnexpectedReply),
         }
     }
 
-    pub async fn fetch_distributed_tokens(&mut self, thread_id: PostId) -> Option<u128> {
-        let res = msg::send_for_reply_as::<_, StorageQueryReply>(
-            self.address_storage.expect(""),
-            StorageQuery::DistributedTokens(thread_id),
-            0,
-            0,
-        )
-        .expect("")
-        .await;
+    pub async fn fetch_distributed_tokens(
+        &mut self,
+        thread_id: PostId,
+    ) -> Result<u128, RewardLogicError> {
+        match self
+            .query_storage(StorageQuery::DistributedTokens(thread_id))
+            .await?
+        {
+            StorageQueryReply::DistributedTokens(distributed_tokens) => Ok(distributed_tokens),
+            _ => Err(RewardLogicError::UnexpectedReply),
+        }
+    }
 
-        match res {
-            Ok(event) => match event {
-                StorageQueryReply::DistributedTokens(distributed_tokens) => {
-                    Some(distributed_tokens)
-                }
-                _ => None,
-            },
-            Err(_) => None,
+    pub async fn fetch_deposit_ledger(
+        &mut self,
+        thread_id: PostId,
+    ) -> Result<Vec<Deposit>, RewardLogicError> {
+        match self
+            .query_storage(StorageQuery::DepositLedger(thread_id))
+            .await?
+        {
+            StorageQueryReply::DepositLedger(deposits) => Ok(deposits),
+            _ => Err(RewardLogicError::UnexpectedReply),
         }
     }
 
-    pub async fn trigger_reward_logic(&mut self, thread_id: PostId) {
-        let _reward_logic_thread = RewardLogicThread::new(self, thread_id).await;
+    /// Distributes the rewards of an expired thread exactly once.
+    ///
+    /// A thread that has already been settled is not paid out again; retried messages get
+    /// `RewardLogicEvent::AlreadySettled` so the caller can carry on. A settlement that stopped
+    /// after some payouts were made is recorded as `Settlement::Failed` and refused, until an
+    /// admin resumes it with `resolve_settlement`.
+    pub async fn trigger_reward_logic(
+        &mut self,
+        thread_id: PostId,
+    ) -> Result<RewardLogicEvent, RewardLogicError> {
+        // Only the logic contract expires threads
+        if self.address_logic != Some(msg::source()) {
+            return Err(RewardLogicError::Unauthorized);
+        }
+
+        match self.settlements.get(&thread_id) {
+            Some(Settlement::Settled(outcome, _)) => {
+                return Ok(RewardLogicEvent::AlreadySettled(thread_id, *outcome))
+            }
+            Some(Settlement::InProgress(_)) => return Err(RewardLogicError::SettlementInProgress),
+            Some(Settlement::Failed(_)) => return Err(RewardLogicError::SettlementFailed),
+            None => {}
+        }
+
+        let outcome = self.settle_thread(thread_id, Vec::new()).await?;
+        Ok(RewardLogicEvent::RewardLogicTriggered(outcome))
+    }
+
+    /// Resumes a settlement that failed, or that was left in progress by a message which
+    /// ran out of gas or trapped, paying out only what is still owed.
+    ///
+    /// A settlement in progress must only be resumed once the message settling it is known
+    /// to have failed, otherwise both would pay out the rest of the pool.
+    pub async fn resolve_settlement(
+        &mut self,
+        thread_id: PostId,
+    ) -> Result<RewardLogicEvent, RewardLogicError> {
+        let payouts = match self.settlements.get(&thread_id) {
+            Some(Settlement::InProgress(payouts)) | Some(Settlement::Failed(payouts)) => {
+                payouts.clone()
+            }
+            _ => return Err(RewardLogicError::SettlementNotPending),
+        };
+
+        let outcome = self.settle_thread(thread_id, payouts).await?;
+        Ok(RewardLogicEvent::SettlementResolved(outcome))
+    }
+
+    /// Settles the thread, skipping the `payouts` an earlier attempt already made, and
+    /// records how far the settlement got.
+    async fn settle_thread(
+        &mut self,
+        thread_id: PostId,
+        payouts: Vec<(ActorId, u128)>,
+    ) -> Result<SettlementOutcome, RewardLogicError> {
+        // Mark the thread before the first await, so concurrent triggers are refused
+        self.settlements
+            .insert(thread_id, Settlement::InProgress(payouts.clone()));
+
+        let mut reward_logic_thread = RewardLogicThread::new(thread_id);
+        reward_logic_thread
+            .expired_thread_data_mut()
+            .transaction_log = Some(payouts);
+        let result = reward_logic_thread.settle(self).await;
+        let payouts = reward_logic_thread.payouts();
+
+        match result {
+            Ok(outcome) => {
+                self.settlements
+                    .insert(thread_id, Settlement::Settled(outcome, payouts));
+                Ok(outcome)
+            }
+            Err(error) => {
+                if payouts.is_empty() {
+                    // Nothing was paid out, the settlement can safely be retried
+                    self.settlements.remove(&thread_id);
+                } else {
+                    self.settlements
+                        .insert(thread_id, Settlement::Failed(payouts));
+                }
+                Err(error)
+            }
+        }
     }
 }
 
 pub struct RewardLogicThread {
     pub thread_id: Option<PostId>,
+    pub reward_policy: RewardPolicy,
     pub distributed_tokens: u128,
     pub graph_rep: ThreadGraph,
     pub all_replies_with_likes: Vec<(PostId, ActorId, u128)>,
@@ -128,87 +387,124 @@ pub struct RewardLogicThread {
 }
 
 impl RewardLogicThread {
-    /// Constructs a new `RewardLogicThread` and initializes it with fetched data.
+    /// Constructs a new `RewardLogicThread` for the given thread.
     ///
     /// # Parameters
     ///
-    /// - `self_ref`: A reference to the calling object, providing methods for fetching data.
-    /// - `thread_id`: The ID of the thread to fetch data for.
+    /// - `thread_id`: The ID of the thread to settle.
     ///
     /// # Returns
     ///
-    /// A new instance of `RewardLogicThread` initialized with fetched data.
-    pub async fn new(self_ref: &mut RewardLogic, thread_id: PostId) -> Self {
+    /// A new instance of `RewardLogicThread` with empty expired thread data.
+    pub fn new(thread_id: PostId) -> Self {
         let mut reward_logic_thread = RewardLogicThread {
             thread_id: Some(thread_id),
-            distributed_tokens: 0,
-            graph_rep: ThreadGraph::default(),
-            all_replies_with_likes: Vec::new(),
-            winner_reply_like_history: Vec::new(),
-            expired_thread_data: None,
+            ..Default::default()
         };
 
         reward_logic_thread.set_expired_thread_data();
 
+        reward_logic_thread
+    }
+
+    /// Fetches the thread data, finds the winners and distributes the rewards.
+    ///
+    /// # Parameters
+    ///
+    /// - `self_ref`: A reference to the calling object, providing methods for fetching data.
+    ///
+    /// # Returns
+    ///
+    /// The `SettlementOutcome` describing how the pool was paid out, or the `RewardLogicError`
+    /// of the first step that failed. Payouts made before the failure are kept in the
+    /// transaction log.
+    pub async fn settle(
+        &mut self,
+        self_ref: &mut RewardLogic,
+    ) -> Result<SettlementOutcome, RewardLogicError> {
+        let thread_id = self.thread_id.ok_or(RewardLogicError::ThreadNotSet)?;
+        let address_ft = self_ref
+            .address_ft
+            .ok_or(RewardLogicError::AddressNotConfigured)?;
+        let address_storage = self_ref
+            .address_storage
+            .ok_or(RewardLogicError::AddressNotConfigured)?;
+        let address_serenium = self_ref
+            .address_treasury
+            .ok_or(RewardLogicError::AddressNotConfigured)?;
+
+        // Split the rewards by the policy of the thread's type
+        let thread = self_ref.fetch_thread(thread_id).await?;
+        self.reward_policy = self_ref.policy_for(thread.thread_type);
+
         // Fetch distributed tokens
-        reward_logic_thread.distributed_tokens = self_ref
-            .fetch_distributed_tokens(thread_id)
-            .await
-            .expect("Error in fetching the distributed tokens");
+        self.distributed_tokens = self_ref.fetch_distributed_tokens(thread_id).await?;
 
         // Fetch reward logic thread data here
-        reward_logic_thread.all_replies_with_likes = self_ref
-            .fetch_all_replies_with_likes(thread_id)
-            .await
-            .expect("Error in fetching all replies with likes");
-
-        reward_logic_thread
-            .expired_thread_data
-            .as_mut()
-            .expect("")
-            .winner_reply = reward_logic_thread.find_winner_reply();
+        self.all_replies_with_likes = self_ref.fetch_all_replies_with_likes(thread_id).await?;
+
+        let Some(mut winner_reply) = self.find_winner_reply() else {
+            // Nobody replied, so the pool is not won by anyone
+            let deposits = self_ref.fetch_deposit_ledger(thread_id).await?;
+            let refund =
+                self.refund_allocation(thread.post_data.owner, &deposits, address_serenium)?;
+            self.pay_out(self_ref, refund, address_ft, address_storage)
+                .await?;
+            return Ok(SettlementOutcome::NoReplies);
+        };
 
         // Fetch like history of winner reply
-        let (reply_id, _, _) = reward_logic_thread
-            .expired_thread_data
-            .as_mut()
-            .expect("")
-            .winner_reply
-            .expect("Winner reply not found");
-        reward_logic_thread.winner_reply_like_history = self_ref
-            .fetch_like_history(thread_id, reply_id)
-            .await
-            .expect("");
+        let (reply_id, _, _) = winner_reply;
+        self.winner_reply_like_history = self_ref.fetch_like_history(thread_id, reply_id).await?;
 
-        reward_logic_thread
-            .expired_thread_data
-            .as_mut()
-            .expect("")
-            .top_liker_winner = reward_logic_thread.find_top_liker_winner();
+        let outcome = match self.find_top_liker_winner() {
+            Some(top_liker_winner) => {
+                self.expired_thread_data_mut().top_liker_winner = Some(top_liker_winner);
+                SettlementOutcome::Distributed
+            }
+            None => {
+                // Nobody liked the winner reply, its author takes the top liker share as well
+                winner_reply.2 += self.reward_policy.top_liker_share(self.distributed_tokens);
+                SettlementOutcome::NoLikers
+            }
+        };
+        self.expired_thread_data_mut().winner_reply = Some(winner_reply);
 
         // Fetch graph rep
-        reward_logic_thread.graph_rep = self_ref
-            .fetch_graph_rep(thread_id)
-            .await
-            .expect("Error in fetching thread's graph rep");
+        self.graph_rep = self_ref.fetch_graph_rep(thread_id).await?;
 
         // Find path winners
-        reward_logic_thread
-            .expired_thread_data
-            .as_mut()
-            .expect("")
-            .path_winners = reward_logic_thread.find_path_winners_tokens();
+        let path_winners = self
+            .find_path_winners_tokens()
+            .ok_or(RewardLogicError::PathNotFound)?;
+        self.expired_thread_data_mut().path_winners = Some(path_winners);
 
         // Distribute rewards
-        reward_logic_thread
-            .distribute_rewards(
-                self_ref.address_ft.unwrap(),
-                self_ref.address_storage.unwrap(),
-                self_ref.admin.unwrap(),
-            )
-            .await;
+        let allocation = self.allocation(address_serenium)?;
+        self.pay_out(self_ref, allocation, address_ft, address_storage)
+            .await?;
 
-        reward_logic_thread
+        Ok(outcome)
+    }
+
+    /// Returns the payouts made so far, in order.
+    pub fn payouts(&self) -> Vec<(ActorId, u128)> {
+        self.expired_thread_data()
+            .transaction_log
+            .clone()
+            .unwrap_or_default()
+    }
+
+    pub fn expired_thread_data_mut(&mut self) -> &mut ExpiredThread {
+        self.expired_thread_data
+            .as_mut()
+            .expect("Expired thread data is not set.")
+    }
+
+    fn expired_thread_data(&self) -> &ExpiredThread {
+        self.expired_thread_data
+            .as_ref()
+            .expect("Expired thread data is not set.")
     }
 
     pub fn set_expired_thread_data(&mut self) {
@@ -216,100 +512,58 @@ impl RewardLogicThread {
         self.expired_thread_data = Some(expired_thread_data);
     }
 
+    /// Finds the reply with the most likes.
+    ///
+    /// Ties go to the lowest `PostId`, which is the earliest posted reply, so the winner
+    /// does not depend on the order storage returns the replies in.
+    ///
+    /// # Returns
+    ///
+    /// - `Some((PostId, ActorId, u128))`: The winning reply, its author and their reward.
+    /// - `None`: If the thread has no replies.
     pub fn find_winner_reply(&self) -> Option<(PostId, ActorId, u128)> {
-        let tokens = (self.distributed_tokens * 3) / 10;
+        let tokens = self.reward_policy.winner_share(self.distributed_tokens);
 
         self.all_replies_with_likes
             .iter()
-            .max_by_key(|(_, _, likes)| likes)
-            .map(|(reply_id, actor_id, _)| (*reply_id, *actor_id, tokens)) // Return the PostId and ActorId of the winning reply
+            .max_by(|(reply_a, _, likes_a), (reply_b, _, likes_b)| {
+                likes_a.cmp(likes_b).then(reply_b.cmp(reply_a))
+            })
+            .map(|(reply_id, actor_id, _)| (*reply_id, *actor_id, tokens))
     }
 
-    /// Finds the `ActorId` of the actor who has given the most likes to the winner has given the most likes.
+    /// Finds the actor who has given the most likes to the winner reply.
     ///
-    /// This function iterates through the `winner_reply_like_history` collection,
-    /// which stores pairs of `ActorId` and the number of likes given by that actor.
-    /// It returns the `ActorId` of the actor with the highest number of likes given.
+    /// The like history lists every liker once, in the order of their first like. Ties go
+    /// to the actor who liked the winner reply earliest.
     ///
     /// # Returns
     ///
-    /// - `Some(ActorId)`: The `ActorId` of the actor who has given the most likes, if the collection is not empty.
-    /// - `None`: If the `winner_reply_like_history` collection is empty.
-    ///
-    /// ```
-    pub fn find_top_liker_winner(&mut self) -> Option<(ActorId, u128)> {
-        let tokens = (self.distributed_tokens * 2) / 10;
+    /// - `Some((ActorId, u128))`: The top liker and their reward.
+    /// - `None`: If nobody liked the winner reply.
+    pub fn find_top_liker_winner(&self) -> Option<(ActorId, u128)> {
+        let tokens = self.reward_policy.top_liker_share(self.distributed_tokens);
 
         self.winner_reply_like_history
             .iter()
-            .max_by_key(|&(_actor_id, likes_given)| *likes_given)
-            .map(|(actor_id, _likes_given)| (*actor_id, tokens))
+            .enumerate()
+            .max_by(|(index_a, (_, likes_a)), (index_b, (_, likes_b))| {
+                likes_a.cmp(likes_b).then(index_b.cmp(index_a))
+            })
+            .map(|(_index, (actor_id, _likes_given))| (*actor_id, tokens))
     }
 
     pub fn find_path_winners(&self) -> Option<Vec<ThreadNode>> {
-        let start_post_id = self.thread_id.expect("Thread ID is not set.");
-        let (target_post_id, _, _) = self
-            .expired_thread_data
-            .as_ref()
-            .expect("Expired thread data is not set.")
-            .winner_reply
-            .expect("Winner reply is not set.");
-
-        // Find the start and target nodes based on PostId
-        let start_node = self
-            .graph_rep
-            .graph
-            .iter()
-            .map(|(node, _)| node)
-            .find(|(post_id, _)| *post_id == start_post_id)
-            .expect("Start node not found in the graph");
+        let start_post_id = self.thread_id?;
+        let (target_post_id, _, _) = self.expired_thread_data().winner_reply?;
 
-        let target_node = self
-            .graph_rep
-            .graph
-            .iter()
-            .map(|(node, _)| node)
-            .find(|(post_id, _)| *post_id == target_post_id)
-            .expect("Target node not found in the graph");
-
-        let mut visited = collections::HashSet::new();
-        let mut queue = collections::VecDeque::new();
-        let mut path = GHashMap::new();
-
-        queue.push_back(*start_node);
-        visited.insert(*start_node);
-
-        while let Some(node) = queue.pop_front() {
-            if node == *target_node {
-                // Reconstruct path
-                let mut current = node;
-                let mut result = Vec::new();
-                while let Some(&prev) = path.get(&current) {
-                    result.push(current);
-                    current = prev;
-                }
-                result.push(*start_node);
-                result.reverse();
-                return Some(result);
-            }
-
-            // Retrieve neighbors from the adjacency list
-            if let Some((_, neighbors)) = self.graph_rep.graph.iter().find(|(id, _)| *id == node) {
-                for &neighbor in neighbors {
-                    if visited.insert(neighbor) {
-                        queue.push_back(neighbor);
-                        path.insert(neighbor, node);
-                    }
-                }
-            }
-        }
-
-        None
+        self.graph_rep.path(start_post_id, target_post_id)
     }
 
     pub fn find_path_winners_tokens(&self) -> Option<(Vec<ThreadNode>, u128)> {
-        let path_winners: Vec<ThreadNode> = self.find_path_winners().expect("");
-        let tokens: u128 = ((self.distributed_tokens * 4) / 10) / path_winners.len() as u128;
+        let path_winners: Vec<ThreadNode> = self.find_path_winners()?;
+        let tokens: u128 =
+            self.reward_policy.path_share(self.distributed_tokens) / path_winners.len() as u128;
         Some((path_winners, tokens))
     }
 
@@ -319,7 +573,7 @@ impl RewardLogicThread {
         amount: u128,
         sender: ActorId,
         recipient: ActorId,
-    ) -> Result<(), ()> {
+    ) -> Result<(), RewardLogicError> {
         let res = msg::send_for_reply_as::<_, FTokenEvent>(
             ft_address_id,
             LogicAction::Transfer {
@@ -330,76 +584,133 @@ impl RewardLogicThread {
             0,
             0,
         )
-        .expect("")
+        .map_err(|_| RewardLogicError::MessageFailed)?
         .await;
 
         match res {
-            Ok(event) => match event {
-                FTokenEvent::Ok => Ok(()),
-                _ => Err(()),
-            },
-            Err(_) => Err(()),
+            Ok(FTokenEvent::Ok) => {
+                // Keep track of every payout, so a settlement is never paid out twice
+                self.expired_thread_data_mut()
+                    .transaction_log
+                    .get_or_insert_with(Vec::new)
+                    .push((recipient, amount));
+                Ok(())
+            }
+            _ => Err(RewardLogicError::FtTransferFailed),
         }
     }
 
-    pub async fn distribute_rewards(
-        &mut self,
-        address_ft: ActorId,
-        address_storage: ActorId,
+    /// Splits the reward pool between the winners, leaving no tokens behind.
+    ///
+    /// # Parameters
+    ///
+    /// - `address_serenium`: The treasury receiving the Serenium commission.
+    ///
+    /// # Returns
+    ///
+    /// The payouts to make, in order: winner reply, path winners, top liker and commission.
+    /// Rounding remainders of the other shares are added to the commission, so the payouts
+    /// always sum up to `distributed_tokens`.
+    pub fn allocation(
+        &self,
         address_serenium: ActorId,
-    ) {
-        let (_reply_id, winner_reply_actor_id, amount) = self
-            .expired_thread_data
-            .as_ref()
-            .expect("")
+    ) -> Result<Vec<(ActorId, u128)>, RewardLogicError> {
+        let expired_thread_data = self.expired_thread_data();
+        let (_reply_id, winner_reply_actor_id, amount) = expired_thread_data
             .winner_reply
-            .expect("");
-        // Distribute reward to winner reply
-        self.transfer_tokens(address_ft, amount, address_storage, winner_reply_actor_id)
-            .await
-            .unwrap();
-
-        // Distribute rewards to path winners
-        if let Some((path_winners, amount_path)) =
-            &self.expired_thread_data.as_ref().expect("").path_winners
-        {
-            let path_winners = path_winners.clone(); // Clone the vector to work with it independently
-            let amount_path = *amount_path;
-
-            for (_post_id, actor_id) in path_winners {
-                self.transfer_tokens(address_ft, amount_path, address_storage, actor_id)
-                    .await
-                    .unwrap();
-            }
+            .ok_or(RewardLogicError::WinnerReplyNotFound)?;
+
+        let mut allocation = vec![(winner_reply_actor_id, amount)];
+        if let Some((path_winners, amount_path)) = &expired_thread_data.path_winners {
+            allocation.extend(
+                path_winners
+                    .iter()
+                    .map(|(_post_id, actor_id)| (*actor_id, *amount_path)),
+            );
+        }
+        if let Some(top_liker_winner) = expired_thread_data.top_liker_winner {
+            allocation.push(top_liker_winner);
         }
 
-        // Distribute rewards to top_liker_winner
-        let (top_liker_winner_actor_id, amount_top_liker_winner) = self
-            .expired_thread_data
-            .as_ref()
-            .expect("")
-            .top_liker_winner
-            .expect("");
+        let allocated = allocation
+            .iter()
+            .try_fold(0u128, |total, (_, amount)| total.checked_add(*amount))
+            .ok_or(RewardLogicError::PayoutMismatch)?;
+        let serenium_tokens = self
+            .distributed_tokens
+            .checked_sub(allocated)
+            .ok_or(RewardLogicError::PayoutMismatch)?;
+        allocation.push((address_serenium, serenium_tokens));
+
+        Ok(allocation)
+    }
 
-        self.transfer_tokens(
-            address_ft,
-            amount_top_liker_winner,
-            address_storage,
-            top_liker_winner_actor_id,
-        )
-        .await
-        .unwrap();
+    /// Splits the pool of a thread nobody replied to. The owner gets back the tokens they
+    /// deposited themselves, the tokens minted for the thread and any other deposit left in
+    /// the pool go to the treasury.
+    pub fn refund_allocation(
+        &self,
+        owner: ActorId,
+        deposits: &[Deposit],
+        address_serenium: ActorId,
+    ) -> Result<Vec<(ActorId, u128)>, RewardLogicError> {
+        let owner_tokens = deposits
+            .iter()
+            .filter(|deposit| {
+                deposit.depositor == owner && !matches!(deposit.source, DepositSource::ThreadMint)
+            })
+            .try_fold(0u128, |total, deposit| total.checked_add(deposit.amount))
+            .ok_or(RewardLogicError::PayoutMismatch)?;
+        let serenium_tokens = self
+            .distributed_tokens
+            .checked_sub(owner_tokens)
+            .ok_or(RewardLogicError::PayoutMismatch)?;
+
+        Ok(vec![
+            (owner, owner_tokens),
+            (address_serenium, serenium_tokens),
+        ])
+    }
 
-        // Transfer Serenium commission
-        let serenium_tokens = self.distributed_tokens / 10;
-        self.transfer_tokens(
-            address_ft,
-            serenium_tokens,
-            address_storage,
-            address_serenium,
-        )
-        .await
-        .expect("Error in transfering tokens to Serenium address");
+    /// Makes the payouts of `allocation` that are not in the transaction log yet.
+    ///
+    /// The log of a resumed settlement must be the start of the allocation, anything else
+    /// means the thread's pool or the policy changed since the earlier attempt. Each payout is
+    /// recorded in `self_ref.settlements` as soon as it is made, so it survives a message that
+    /// runs out of gas before finishing.
+    pub async fn pay_out(
+        &mut self,
+        self_ref: &mut RewardLogic,
+        allocation: Vec<(ActorId, u128)>,
+        address_ft: ActorId,
+        address_storage: ActorId,
+    ) -> Result<(), RewardLogicError> {
+        let thread_id = self.thread_id.ok_or(RewardLogicError::ThreadNotSet)?;
+        // Small pools round some shares down to nothing
+        let allocation: Vec<(ActorId, u128)> = allocation
+            .into_iter()
+            .filter(|(_, amount)| *amount > 0)
+            .collect();
+
+        let already_paid = self.payouts();
+        if !allocation.starts_with(&already_paid) {
+            return Err(RewardLogicError::PayoutMismatch);
+        }
+
+        for (recipient, amount) in allocation.into_iter().skip(already_paid.len()) {
+            self.transfer_tokens(address_ft, amount, address_storage, recipient)
+                .await?;
+            self_ref
+                .settlements
+                .insert(thread_id, Settlement::InProgress(self.payouts()));
+        }
+
+        // Every token of the pool must have been paid out exactly once
+        let paid_out: u128 = self.payouts().iter().map(|(_, amount)| amount).sum();
+        if paid_out != self.distributed_tokens {
+            return Err(RewardLogicError::PayoutMismatch);
+        }
+        Ok(())
     }
 }
 
@@ -412,6 +723,7 @@ impl Default for RewardLogicThread {
     fn default() -> Self {
         RewardLogicThread {
             thread_id: None,
+            reward_policy: RewardPolicy::default(),
             distributed_tokens: 0,
             graph_rep: ThreadGraph::default(),
             all_replies_with_likes: Vec::new(),
@@ -445,33 +757,237 @@ impl Default for ExpiredThread {
     }
 }
 
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, PartialEq, Eq)]
+#[codec(crate = gstd::codec)]
+#[scale_info(crate = gstd::scale_info)]
+pub enum RewardLogicError {
+    Roles(RolesError),
+    // Sender is not the registered logic contract
+    Unauthorized,
+    AddressNotConfigured,
+    // Sending a message or receiving its reply failed
+    MessageFailed,
+    UnexpectedReply,
+    Storage(StorageError),
+    FtTransferFailed,
+    WinnerReplyNotFound,
+    PathNotFound,
+    ThreadNotSet,
+    // Rewards of the thread are already being distributed
+    SettlementInProgress,
+    // A previous settlement of the thread stopped after paying out part of the rewards
+    SettlementFailed,
+    // The thread has no failed or interrupted settlement to resolve
+    SettlementNotPending,
+    // Shares of the reward policy do not add up to 10_000 basis points
+    InvalidRewardPolicy,
+    // Payouts of a settlement do not add up to the thread's distributed tokens
+    PayoutMismatch,
+    // Actions of the scope are halted by an admin
+    Paused(PauseScope),
+    // The contract has no actions in the pause scope
+    UnsupportedPauseScope(PauseScope),
+}
+
+/// Configuration of a new reward logic contract. Addresses and policies left out can be set
+/// later with the corresponding actions.
+#[derive(Default, Encode, Decode, TypeInfo)]
+#[codec(crate = gstd::codec)]
+#[scale_info(crate = gstd::scale_info)]
+pub struct InitRewardLogic {
+    // Owner of the contract roles, defaults to the deployer
+    pub owner: Option<ActorId>,
+    pub address_ft: Option<ActorId>,
+    pub address_logic: Option<ActorId>,
+    pub address_storage: Option<ActorId>,
+    pub address_treasury: Option<ActorId>,
+    // Defaults to `RewardPolicy::default()`
+    pub reward_policy: Option<RewardPolicy>,
+}
+
 #[derive(Encode, Decode, TypeInfo)]
 #[codec(crate = gstd::codec)]
 #[scale_info(crate = gstd::scale_info)]
 pub enum RewardLogicAction {
+    GrantRole(Role, ActorId),
+    RevokeRole(Role, ActorId),
+    // First step of an ownership transfer, completed by the new owner with `AcceptOwnership`
+    TransferOwnership(ActorId),
+    AcceptOwnership,
     AddAddressFT(ActorId),
     AddAddressLogic(ActorId),
     AddAddressStorage(ActorId),
+    // Treasurer only
+    SetTreasuryAddress(ActorId),
     TriggerRewardLogic(PostId),
+    // Admin only, resumes the failed or interrupted settlement of a thread
+    ResolveSettlement(PostId),
+    SetRewardPolicy(RewardPolicy),
+    // Override the reward policy for a thread type, `None` falls back to the default policy
+    SetThreadTypeRewardPolicy(ThreadType, Option<RewardPolicy>),
+    // Halts or resumes the actions of a scope, only `All` and `Settlement` apply here
+    SetPause(PauseScope, bool),
 }
 
 #[derive(Encode, Decode, TypeInfo)]
 #[codec(crate = gstd::codec)]
 #[scale_info(crate = gstd::scale_info)]
 pub enum RewardLogicEvent {
+    RoleGranted,
+    RoleRevoked,
+    OwnershipTransferStarted,
+    OwnershipTransferred,
     FTAddressAdded,
     LogicAddressAdded,
     StorageAddressAdded,
-    RewardLogicTriggered,
+    TreasuryAddressSet,
+    RewardLogicTriggered(SettlementOutcome),
+    // Rewards of the thread were distributed by an earlier message
+    AlreadySettled(PostId, SettlementOutcome),
+    SettlementResolved(SettlementOutcome),
+    RewardPolicySet,
+    ThreadTypeRewardPolicySet,
+    PauseSet,
 }
 
 pub struct ContractMetadata;
 
 impl Metadata for ContractMetadata {
-    type Init = ();
-    type Handle = InOut<RewardLogicAction, RewardLogicEvent>;
+    type Init = In<InitRewardLogic>;
+    type Handle = InOut<RewardLogicAction, Result<RewardLogicEvent, RewardLogicError>>;
     type Reply = ();
     type Others = ();
     type Signal = ();
     type State = Out<RewardLogic>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reward_logic_thread(distributed_tokens: u128) -> RewardLogicThread {
+        let mut reward_logic_thread = RewardLogicThread::new(1);
+        reward_logic_thread.distributed_tokens = distributed_tokens;
+        reward_logic_thread
+    }
+
+    #[test]
+    fn winner_reply_tie_goes_to_lowest_post_id() {
+        let mut reward_logic_thread = reward_logic_thread(100);
+        reward_logic_thread.all_replies_with_likes = vec![
+            (3, ActorId::from(3), 5),
+            (2, ActorId::from(2), 5),
+            (4, ActorId::from(4), 1),
+        ];
+
+        assert_eq!(
+            reward_logic_thread.find_winner_reply(),
+            Some((2, ActorId::from(2), 30))
+        );
+    }
+
+    #[test]
+    fn winner_reply_is_most_liked() {
+        let mut reward_logic_thread = reward_logic_thread(100);
+        reward_logic_thread.all_replies_with_likes =
+            vec![(2, ActorId::from(2), 1), (3, ActorId::from(3), 7)];
+
+        assert_eq!(
+            reward_logic_thread.find_winner_reply(),
+            Some((3, ActorId::from(3), 30))
+        );
+    }
+
+    #[test]
+    fn no_winner_reply_without_replies() {
+        assert_eq!(reward_logic_thread(100).find_winner_reply(), None);
+    }
+
+    #[test]
+    fn top_liker_tie_goes_to_earliest_liker() {
+        let mut reward_logic_thread = reward_logic_thread(100);
+        reward_logic_thread.winner_reply_like_history = vec![
+            (ActorId::from(1), 2),
+            (ActorId::from(2), 3),
+            (ActorId::from(3), 3),
+        ];
+
+        assert_eq!(
+            reward_logic_thread.find_top_liker_winner(),
+            Some((ActorId::from(2), 20))
+        );
+    }
+
+    #[test]
+    fn allocation_sends_rounding_dust_to_treasury() {
+        let treasury = ActorId::from(100);
+        let mut reward_logic_thread = reward_logic_thread(10);
+        let expired_thread_data = reward_logic_thread.expired_thread_data_mut();
+        expired_thread_data.winner_reply = Some((4, ActorId::from(3), 3));
+        expired_thread_data.path_winners = Some((
+            vec![
+                (1, ActorId::from(1)),
+                (2, ActorId::from(2)),
+                (4, ActorId::from(3)),
+            ],
+            1,
+        ));
+        expired_thread_data.top_liker_winner = Some((ActorId::from(1), 2));
+
+        let allocation = reward_logic_thread
+            .allocation(treasury)
+            .expect("Pool is not over-allocated");
+
+        assert_eq!(allocation.last(), Some(&(treasury, 2)));
+        assert_eq!(
+            allocation.iter().map(|(_, amount)| amount).sum::<u128>(),
+            10
+        );
+    }
+
+    #[test]
+    fn refund_allocation_returns_only_owner_deposits() {
+        let owner = ActorId::from(1);
+        let treasury = ActorId::from(100);
+        let deposits = vec![
+            Deposit {
+                depositor: owner,
+                source: DepositSource::ThreadMint,
+                amount: 1,
+            },
+            Deposit {
+                depositor: owner,
+                source: DepositSource::Reply(2),
+                amount: 1,
+            },
+            Deposit {
+                depositor: ActorId::from(2),
+                source: DepositSource::Like(2),
+                amount: 3,
+            },
+        ];
+
+        assert_eq!(
+            reward_logic_thread(5).refund_allocation(owner, &deposits, treasury),
+            Ok(vec![(owner, 1), (treasury, 4)])
+        );
+        assert_eq!(
+            reward_logic_thread(1).refund_allocation(owner, &deposits[..1], treasury),
+            Ok(vec![(owner, 0), (treasury, 1)])
+        );
+    }
+
+    #[test]
+    fn reward_policy_must_cover_whole_pool() {
+        assert_eq!(RewardPolicy::default().validate(), Ok(()));
+
+        let reward_policy = RewardPolicy {
+            commission_bps: 0,
+            ..Default::default()
+        };
+        assert_eq!(
+            reward_policy.validate(),
+            Err(RewardLogicError::InvalidRewardPolicy)
+        );
+    }
+}
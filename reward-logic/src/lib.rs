@@ -1,7 +1,11 @@
 #![no_std]
 
 use gstd::{async_main, msg};
-use reward_logic_io::{RewardLogic, RewardLogicAction, RewardLogicEvent};
+use io::pause::PauseScope;
+use io::roles::{Role, Roles};
+use reward_logic_io::{
+    InitRewardLogic, RewardLogic, RewardLogicAction, RewardLogicError, RewardLogicEvent,
+};
 
 static mut REWARD_LOGIC: Option<RewardLogic> = None;
 
@@ -11,57 +15,130 @@ fn reward_logic_mut() -> &'static mut RewardLogic {
 
 #[no_mangle]
 extern fn init() {
-    let reward_logic = RewardLogic::new();
+    let init: InitRewardLogic = msg::load().expect("Could not load InitRewardLogic");
 
-    unsafe { REWARD_LOGIC = Some(reward_logic) }
+    let mut reward_logic = RewardLogic {
+        roles: Roles::new(init.owner.unwrap_or_else(msg::source)),
+        address_ft: init.address_ft,
+        address_logic: init.address_logic,
+        address_storage: init.address_storage,
+        address_treasury: init.address_treasury,
+        ..RewardLogic::new()
+    };
+    if let Some(reward_policy) = init.reward_policy {
+        reward_logic
+            .set_reward_policy(reward_policy)
+            .expect("Invalid reward policy");
+    }
 
-    reward_logic_mut().admin = Some(msg::source());
+    unsafe { REWARD_LOGIC = Some(reward_logic) }
 }
 
 #[async_main]
 async fn main() {
-    let action: RewardLogicAction = msg::load().expect("");
+    let action: RewardLogicAction = msg::load().expect("Could not load Action");
     let reward_logic = reward_logic_mut();
+    let result = process_action(reward_logic, action).await;
+    msg::reply(result, 0).expect("Failed to reply to Reward Logic Action");
+}
+
+async fn process_action(
+    reward_logic: &mut RewardLogic,
+    action: RewardLogicAction,
+) -> Result<RewardLogicEvent, RewardLogicError> {
     match action {
+        RewardLogicAction::GrantRole(role, actor) => {
+            reward_logic
+                .roles
+                .grant(msg::source(), role, actor)
+                .map_err(RewardLogicError::Roles)?;
+            Ok(RewardLogicEvent::RoleGranted)
+        }
+
+        RewardLogicAction::RevokeRole(role, actor) => {
+            reward_logic
+                .roles
+                .revoke(msg::source(), role, actor)
+                .map_err(RewardLogicError::Roles)?;
+            Ok(RewardLogicEvent::RoleRevoked)
+        }
+
+        RewardLogicAction::TransferOwnership(new_owner) => {
+            reward_logic
+                .roles
+                .transfer_ownership(msg::source(), new_owner)
+                .map_err(RewardLogicError::Roles)?;
+            Ok(RewardLogicEvent::OwnershipTransferStarted)
+        }
+
+        RewardLogicAction::AcceptOwnership => {
+            reward_logic
+                .roles
+                .accept_ownership(msg::source())
+                .map_err(RewardLogicError::Roles)?;
+            Ok(RewardLogicEvent::OwnershipTransferred)
+        }
+
         RewardLogicAction::AddAddressFT(address) => {
-            if reward_logic.admin.expect("") != msg::source() {
-                panic!("Add Address FT Action can only be called by admin")
-            }
+            reward_logic.ensure_role(msg::source(), Role::Admin)?;
             reward_logic.address_ft = Some(address);
-            msg::reply(RewardLogicEvent::FTAddressAdded, 0).expect("");
+            Ok(RewardLogicEvent::FTAddressAdded)
         }
 
         RewardLogicAction::AddAddressLogic(address) => {
-            if reward_logic.admin.expect("") != msg::source() {
-                panic!("Add Address Logic Action can only be called by admin")
-            }
+            reward_logic.ensure_role(msg::source(), Role::Admin)?;
             reward_logic.address_logic = Some(address);
-            msg::reply(RewardLogicEvent::LogicAddressAdded, 0).expect("");
+            Ok(RewardLogicEvent::LogicAddressAdded)
         }
 
         RewardLogicAction::AddAddressStorage(address) => {
-            if reward_logic.admin.expect("") != msg::source() {
-                panic!("Add Address Storage Action can only be called by admin")
-            }
+            reward_logic.ensure_role(msg::source(), Role::Admin)?;
             reward_logic.address_storage = Some(address);
-            msg::reply(RewardLogicEvent::StorageAddressAdded, 0).expect("");
+            Ok(RewardLogicEvent::StorageAddressAdded)
+        }
+
+        RewardLogicAction::SetTreasuryAddress(address) => {
+            reward_logic.ensure_role(msg::source(), Role::Treasurer)?;
+            reward_logic.address_treasury = Some(address);
+            Ok(RewardLogicEvent::TreasuryAddressSet)
         }
 
         RewardLogicAction::TriggerRewardLogic(thread_id) => {
-            reward_logic.trigger_reward_logic(thread_id).await;
-            msg::reply(RewardLogicEvent::RewardLogicTriggered, 0).expect("");
+            reward_logic.ensure_not_paused(PauseScope::Settlement)?;
+            reward_logic.trigger_reward_logic(thread_id).await
+        }
+
+        RewardLogicAction::ResolveSettlement(thread_id) => {
+            reward_logic.ensure_role(msg::source(), Role::Admin)?;
+            reward_logic.ensure_not_paused(PauseScope::Settlement)?;
+            reward_logic.resolve_settlement(thread_id).await
+        }
+
+        RewardLogicAction::SetRewardPolicy(reward_policy) => {
+            reward_logic.ensure_role(msg::source(), Role::Treasurer)?;
+            reward_logic.set_reward_policy(reward_policy)?;
+            Ok(RewardLogicEvent::RewardPolicySet)
+        }
+
+        RewardLogicAction::SetThreadTypeRewardPolicy(thread_type, reward_policy) => {
+            reward_logic.ensure_role(msg::source(), Role::Treasurer)?;
+            reward_logic.set_thread_type_policy(thread_type, reward_policy)?;
+            Ok(RewardLogicEvent::ThreadTypeRewardPolicySet)
+        }
+
+        RewardLogicAction::SetPause(scope, paused) => {
+            reward_logic.ensure_role(msg::source(), Role::Admin)?;
+            reward_logic.set_pause(scope, paused)?;
+            Ok(RewardLogicEvent::PauseSet)
         }
     }
 }
 
 #[no_mangle]
 extern fn state() {
-    let thread_logic = unsafe {
-        REWARD_LOGIC
-            .take()
-            .expect("Unexpected error in taking state")
-    };
-    msg::reply::<RewardLogic>(thread_logic, 0).expect(
+    // Borrow the state, the next `handle` must still find it in place
+    let reward_logic = unsafe { REWARD_LOGIC.as_ref().expect("State is not initialized") };
+    msg::reply::<&RewardLogic>(reward_logic, 0).expect(
         "Failed to encode or reply with `<ContractMetadata as Metadata>::State` from `state()`",
     );
 }
@@ -1,8 +1,23 @@
 #![no_std]
 
-use gmeta::{InOut, Metadata};
-use gstd::{collections::HashMap as GHashMap, msg, prelude::*, ActorId};
-use io::{Post, PostId, Thread, ThreadGraph, ThreadNode, ThreadReply, ThreadStatus, ThreadType};
+use core::{cmp::Reverse, ops::Bound};
+use gmeta::{In, InOut, Metadata};
+use gstd::{
+    collections::{BTreeMap, BTreeSet, HashMap as GHashMap},
+    msg,
+    prelude::*,
+    ActorId,
+};
+use io::roles::{Role, Roles, RolesError};
+use io::{
+    FeaturedReply, Post, PostId, ReportReason, Thread, ThreadGraph, ThreadNode, ThreadReply,
+    ThreadStatus, ThreadType, Timestamp, REPLY_DEPOSIT, THREAD_MINT_AMOUNT,
+};
+
+// Upper bound on the number of items returned by a single page of a frontend query
+pub const MAX_PAGE_SIZE: u32 = 50;
+// Number of reports after which a thread or reply is hidden
+pub const DEFAULT_REPORT_THRESHOLD: u64 = 3;
 
 #[derive(Encode, Decode, TypeInfo)]
 #[codec(crate = gstd::codec)]
@@ -11,6 +26,18 @@ pub struct QueryThread {
     pub post_data: Post,
     pub thread_type: ThreadType,
     pub thread_status: ThreadStatus,
+    pub featured_reply: FeaturedReply,
+}
+
+impl From<&Thread> for QueryThread {
+    fn from(thread: &Thread) -> Self {
+        QueryThread {
+            post_data: thread.post_data.clone(),
+            thread_type: thread.thread_type,
+            thread_status: thread.thread_status.clone(),
+            featured_reply: thread.featured_reply,
+        }
+    }
 }
 
 #[derive(Encode, Decode, TypeInfo)]
@@ -21,171 +48,1718 @@ pub struct QueryReply {
     pub thread_id: PostId,
 }
 
+#[derive(Default, Encode, Decode, TypeInfo)]
+#[codec(crate = gstd::codec)]
+#[scale_info(crate = gstd::scale_info)]
+pub struct Page<T, C = PostId> {
+    pub items: Vec<T>,
+    // Pass as the cursor of the next query to continue, `None` on the last page
+    pub next_cursor: Option<C>,
+}
+
+impl<T, C> Page<T, C> {
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Page<U, C> {
+        Page {
+            items: self.items.into_iter().map(f).collect(),
+            next_cursor: self.next_cursor,
+        }
+    }
+}
+
+/// Position of the last item of a sorted page: the value it was sorted by and its id.
+///
+/// The next page starts right after that position in the sort order, so the cursor stays
+/// valid when its post is hidden or removed, and posts added between pages do not shift
+/// the items already returned.
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, Copy, Default, PartialEq, Eq)]
+#[codec(crate = gstd::codec)]
+#[scale_info(crate = gstd::scale_info)]
+pub struct Cursor {
+    // Likes or replies of the post for the sorts by them, 0 for the sorts by age
+    pub key: u128,
+    pub post_id: PostId,
+}
+
+#[derive(Encode, Decode, TypeInfo, Clone, Copy, Default)]
+#[codec(crate = gstd::codec)]
+#[scale_info(crate = gstd::scale_info)]
+pub enum ThreadSort {
+    #[default]
+    Newest,
+    // Most likes across all replies of the thread
+    MostLiked,
+    MostReplies,
+}
+
+#[derive(Encode, Decode, TypeInfo, Clone, Copy, Default)]
+#[codec(crate = gstd::codec)]
+#[scale_info(crate = gstd::scale_info)]
+pub enum ReplySort {
+    // Conversation order
+    #[default]
+    Oldest,
+    Newest,
+    MostLiked,
+}
+
+/// Criteria a post has to match to be returned by a frontend query. Unset criteria match
+/// every post, time bounds are inclusive.
+#[derive(Encode, Decode, TypeInfo, Clone, Default)]
+#[codec(crate = gstd::codec)]
+#[scale_info(crate = gstd::scale_info)]
+pub struct PostFilter {
+    pub owner: Option<ActorId>,
+    pub posted_from: Option<Timestamp>,
+    pub posted_to: Option<Timestamp>,
+}
+
+impl PostFilter {
+    pub fn matches(&self, post: &Post) -> bool {
+        if let Some(owner) = self.owner {
+            if post.owner != owner {
+                return false;
+            }
+        }
+        if let Some(posted_from) = self.posted_from {
+            if post.posted_at < posted_from {
+                return false;
+            }
+        }
+        if let Some(posted_to) = self.posted_to {
+            if post.posted_at > posted_to {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[derive(Encode, Decode, TypeInfo, Clone, Default)]
+#[codec(crate = gstd::codec)]
+#[scale_info(crate = gstd::scale_info)]
+pub struct ThreadFilter {
+    pub thread_type: Option<ThreadType>,
+    pub thread_status: Option<ThreadStatus>,
+    pub post: PostFilter,
+}
+
+impl ThreadFilter {
+    pub fn matches(&self, thread: &Thread) -> bool {
+        if let Some(thread_type) = self.thread_type {
+            if thread.thread_type != thread_type {
+                return false;
+            }
+        }
+        if let Some(thread_status) = &self.thread_status {
+            if thread.thread_status != *thread_status {
+                return false;
+            }
+        }
+        self.post.matches(&thread.post_data)
+    }
+}
+
+#[derive(Encode, Decode, TypeInfo, Clone, Default)]
+#[codec(crate = gstd::codec)]
+#[scale_info(crate = gstd::scale_info)]
+pub struct ThreadsQuery {
+    pub filter: ThreadFilter,
+    pub sort: ThreadSort,
+    // `next_cursor` of the previous page, `None` for the first page
+    pub cursor: Option<Cursor>,
+    // Capped at `MAX_PAGE_SIZE`
+    pub limit: u32,
+}
+
+#[derive(Encode, Decode, TypeInfo, Clone, Default)]
+#[codec(crate = gstd::codec)]
+#[scale_info(crate = gstd::scale_info)]
+pub struct RepliesQuery {
+    pub filter: PostFilter,
+    pub sort: ReplySort,
+    // `next_cursor` of the previous page, `None` for the first page
+    pub cursor: Option<Cursor>,
+    // Capped at `MAX_PAGE_SIZE`
+    pub limit: u32,
+}
+
+/// Collects the first `limit` of the ordered `items`, each with its cursor, into a page.
+fn page_of<C: Copy, T>(items: impl Iterator<Item = (C, T)>, limit: u32) -> Page<T, C> {
+    let limit = limit.clamp(1, MAX_PAGE_SIZE) as usize;
+
+    let mut page: Vec<(C, T)> = items.take(limit + 1).collect();
+    let next_cursor = if page.len() > limit {
+        page.truncate(limit);
+        page.last().map(|(cursor, _)| *cursor)
+    } else {
+        None
+    };
+
+    Page {
+        items: page.into_iter().map(|(_, item)| item).collect(),
+        next_cursor,
+    }
+}
+
+/// Range of the keys below the `cursor` key, read backwards by descending pages.
+fn below<K>(cursor: Option<K>) -> (Bound<K>, Bound<K>) {
+    (
+        Bound::Unbounded,
+        cursor.map_or(Bound::Unbounded, Bound::Excluded),
+    )
+}
+
+/// Total likes of the thread's replies and the number of its replies, the keys of
+/// `ThreadStorage::threads_by_likes` and `ThreadStorage::threads_by_replies`.
+fn sort_keys(thread: &Thread) -> (u128, u128) {
+    let likes = thread.replies.iter().map(|(_, reply)| reply.likes).sum();
+    (likes, thread.replies.len() as u128)
+}
+
+/// Pairs a reply of a page sorted by age with its cursor.
+fn by_age((post_id, reply): &(PostId, ThreadReply)) -> (Cursor, &ThreadReply) {
+    let cursor = Cursor {
+        key: 0,
+        post_id: *post_id,
+    };
+    (cursor, reply)
+}
+
+#[derive(Encode, Decode, TypeInfo)]
+#[codec(crate = gstd::codec)]
+#[scale_info(crate = gstd::scale_info)]
+pub struct LikeActivity {
+    pub thread_id: PostId,
+    pub reply_id: PostId,
+    pub likes_given: u128,
+}
+
+#[derive(Encode, Decode, TypeInfo, Clone)]
+#[codec(crate = gstd::codec)]
+#[scale_info(crate = gstd::scale_info)]
+pub struct Report {
+    pub reporter: ActorId,
+    pub reason: ReportReason,
+}
+
+/// A reported thread or reply waiting in the moderation queue.
+#[derive(Encode, Decode, TypeInfo, Clone)]
+#[codec(crate = gstd::codec)]
+#[scale_info(crate = gstd::scale_info)]
+pub struct ReportedPost {
+    pub thread_id: PostId,
+    // Equal to `thread_id` for a reported thread
+    pub post_id: PostId,
+    pub reports: Vec<Report>,
+}
+
+/// Posts and likes of a single actor, so they can be queried without scanning every thread.
 #[derive(Default)]
+pub struct ActorIndex {
+    pub threads: BTreeSet<PostId>,
+    // Replies posted by the actor, with the thread they belong to
+    pub replies: BTreeMap<PostId, PostId>,
+    // Replies liked by the actor, with the thread they belong to
+    pub likes: BTreeMap<PostId, PostId>,
+}
+
+#[derive(Encode, Decode, TypeInfo, Clone)]
+#[codec(crate = gstd::codec)]
+#[scale_info(crate = gstd::scale_info)]
+pub enum DepositSource {
+    ThreadMint,
+    Reply(PostId),
+    Like(PostId),
+}
+
+#[derive(Encode, Decode, TypeInfo, Clone)]
+#[codec(crate = gstd::codec)]
+#[scale_info(crate = gstd::scale_info)]
+pub struct Deposit {
+    pub depositor: ActorId,
+    pub source: DepositSource,
+    pub amount: u128,
+}
+
 pub struct ThreadStorage {
-    pub threads: GHashMap<PostId, Thread>,
-    pub admin: Option<ActorId>,
+    pub threads: BTreeMap<PostId, Thread>,
+    // Threads ordered by the total likes and the number of their replies, then by id
+    pub threads_by_likes: BTreeSet<(u128, PostId)>,
+    pub threads_by_replies: BTreeSet<(u128, PostId)>,
+    // Replies of each thread ordered by likes, then oldest first when read backwards
+    pub replies_by_likes: GHashMap<PostId, BTreeSet<(u128, Reverse<PostId>)>>,
+    // Ledger of the tokens deposited into each thread's reward pool
+    pub deposits: GHashMap<PostId, Vec<Deposit>>,
+    // Next id handed out to a pushed thread or reply
+    pub next_post_id: PostId,
+    pub actor_indexes: GHashMap<ActorId, ActorIndex>,
+    // Moderation queue, in the order the posts were created
+    pub reported_posts: BTreeMap<PostId, ReportedPost>,
+    pub report_threshold: u64,
+    pub roles: Roles,
     pub address_logic_contract: Option<ActorId>,
+    pub address_reward_logic_contract: Option<ActorId>,
 }
 
 impl ThreadStorage {
     pub fn new() -> Self {
         ThreadStorage {
-            threads: GHashMap::new(),
-            admin: None,
+            threads: BTreeMap::new(),
+            threads_by_likes: BTreeSet::new(),
+            threads_by_replies: BTreeSet::new(),
+            replies_by_likes: GHashMap::new(),
+            deposits: GHashMap::new(),
+            next_post_id: 1,
+            actor_indexes: GHashMap::new(),
+            reported_posts: BTreeMap::new(),
+            report_threshold: DEFAULT_REPORT_THRESHOLD,
+            roles: Roles::default(),
             address_logic_contract: None,
+            address_reward_logic_contract: None,
         }
     }
 
-    pub fn push_thread(&mut self, mut thread: Thread) {
+    /// Allocates a unique `PostId`, independent of the block the post was created in.
+    pub fn allocate_post_id(&mut self) -> PostId {
+        let post_id = self.next_post_id;
+        self.next_post_id = self
+            .next_post_id
+            .checked_add(1)
+            .expect("PostId space exhausted");
+        post_id
+    }
+
+    pub fn push_thread(&mut self, mut thread: Thread) -> PostId {
+        let thread_id = self.allocate_post_id();
+        thread.post_data.post_id = thread_id;
+
         // Create a new node on graph
         let new_node: ThreadNode = (thread.post_data.post_id, thread.post_data.owner);
 
         thread.graph_rep.add_node(new_node);
 
+        // The minted tokens open the thread's reward pool
+        thread.distributed_tokens += THREAD_MINT_AMOUNT;
+        self.deposits.insert(
+            thread.post_data.post_id,
+            vec![Deposit {
+                depositor: thread.post_data.owner,
+                source: DepositSource::ThreadMint,
+                amount: THREAD_MINT_AMOUNT,
+            }],
+        );
+
+        self.actor_indexes
+            .entry(thread.post_data.owner)
+            .or_default()
+            .threads
+            .insert(thread_id);
+
+        self.replies_by_likes.insert(
+            thread_id,
+            thread
+                .replies
+                .iter()
+                .map(|(reply_id, reply)| (reply.likes, Reverse(*reply_id)))
+                .collect(),
+        );
         self.threads.insert(thread.post_data.post_id, thread);
+        self.index_thread(thread_id);
+
+        thread_id
     }
 
-    pub fn push_reply(&mut self, thread_id: PostId, reply: ThreadReply, ref_node: PostId) {
+    pub fn push_reply(
+        &mut self,
+        thread_id: PostId,
+        mut reply: ThreadReply,
+        ref_node: PostId,
+    ) -> Result<PostId, StorageError> {
+        // The reward pool of an expired thread is frozen
+        self.ensure_thread_active(thread_id)?;
+
+        // Replies answer the thread itself or one of its replies
+        if self.thread(thread_id)?.graph_rep.node(ref_node).is_none() {
+            return Err(StorageError::RefNodeNotFound);
+        }
+
+        let reply_id = self.allocate_post_id();
+        reply.post_data.post_id = reply_id;
+
+        self.unindex_thread(thread_id);
         if let Some(thread) = self.threads.get_mut(&thread_id) {
             // Push to graph_rep
             let new_node: ThreadNode = (reply.post_data.post_id, reply.post_data.owner);
 
+            thread.graph_rep.add_node(new_node);
             thread.graph_rep.add_edge(ref_node, new_node);
 
+            // The reply deposit goes into the thread's reward pool
+            thread.distributed_tokens += REPLY_DEPOSIT;
+            self.deposits.entry(thread_id).or_default().push(Deposit {
+                depositor: reply.post_data.owner,
+                source: DepositSource::Reply(reply.post_data.post_id),
+                amount: REPLY_DEPOSIT,
+            });
+
+            self.actor_indexes
+                .entry(reply.post_data.owner)
+                .or_default()
+                .replies
+                .insert(reply_id, thread_id);
+
+            self.replies_by_likes
+                .entry(thread_id)
+                .or_default()
+                .insert((reply.likes, Reverse(reply_id)));
+
             thread.replies.push((reply.post_data.post_id, reply));
         }
+        self.index_thread(thread_id);
+
+        Ok(reply_id)
     }
 
-    pub fn like_reply(&mut self, thread_id: PostId, reply_id: PostId, like_count: u128) {
-        // Retrieve the mutable reference to the thread by its `thread_id`
-        if let Some(thread) = self.threads.get_mut(&thread_id) {
-            // Find the mutable reference to the `ThreadReply` tuple within the thread
-            if let Some((_, reply)) = thread.replies.iter_mut().find(|(id, _)| *id == reply_id) {
-                // Increment the reply's likes by the specified amount
-                reply.likes += like_count;
+    pub fn like_reply(
+        &mut self,
+        thread_id: PostId,
+        reply_id: PostId,
+        liker: ActorId,
+        like_count: u128,
+    ) -> Result<(), StorageError> {
+        if like_count == 0 {
+            return Err(StorageError::InvalidLikeCount);
+        }
+        // The reward pool of an expired thread is frozen
+        self.ensure_thread_active(thread_id)?;
+        self.reply(thread_id, reply_id)?;
+
+        self.unindex_thread(thread_id);
+        let thread = self
+            .threads
+            .get_mut(&thread_id)
+            .ok_or(StorageError::ThreadNotFound)?;
+
+        // Find the mutable reference to the `ThreadReply` tuple within the thread
+        let (_, reply) = thread
+            .replies
+            .iter_mut()
+            .find(|(id, _)| *id == reply_id)
+            .ok_or(StorageError::ReplyNotFound)?;
+
+        // Increment the reply's likes by the specified amount
+        if let Some(replies_by_likes) = self.replies_by_likes.get_mut(&thread_id) {
+            replies_by_likes.remove(&(reply.likes, Reverse(reply_id)));
+            replies_by_likes.insert((reply.likes + like_count, Reverse(reply_id)));
+        }
+        reply.likes += like_count;
+
+        // Aggregate the likes given per actor in the like history
+        match reply
+            .like_history
+            .iter_mut()
+            .find(|(actor_id, _)| *actor_id == liker)
+        {
+            Some((_, likes_given)) => *likes_given += like_count,
+            None => reply.like_history.push((liker, like_count)),
+        }
+
+        // The tokens spent on likes go into the thread's reward pool
+        thread.distributed_tokens += like_count;
+        self.deposits.entry(thread_id).or_default().push(Deposit {
+            depositor: liker,
+            source: DepositSource::Like(reply_id),
+            amount: like_count,
+        });
+
+        self.actor_indexes
+            .entry(liker)
+            .or_default()
+            .likes
+            .insert(reply_id, thread_id);
+        self.index_thread(thread_id);
+
+        Ok(())
+    }
+
+    /// Changes which reply is featured for the thread. Pinned and accepted replies must
+    /// belong to the thread, and only question threads can have an accepted answer.
+    pub fn set_featured_reply(
+        &mut self,
+        thread_id: PostId,
+        featured_reply: FeaturedReply,
+    ) -> Result<(), StorageError> {
+        let thread = self
+            .threads
+            .get_mut(&thread_id)
+            .ok_or(StorageError::ThreadNotFound)?;
+
+        match featured_reply {
+            FeaturedReply::MostLiked | FeaturedReply::Newest => {}
+            FeaturedReply::Pinned(reply_id) | FeaturedReply::Accepted(reply_id) => {
+                if !thread.replies.iter().any(|(id, _)| *id == reply_id) {
+                    return Err(StorageError::ReplyNotFound);
+                }
             }
         }
+        if matches!(featured_reply, FeaturedReply::Accepted(_))
+            && thread.thread_type != ThreadType::Question
+        {
+            return Err(StorageError::NotAQuestion);
+        }
+
+        thread.featured_reply = featured_reply;
+
+        Ok(())
     }
 
-    pub fn change_status_thread(&mut self, thread_id: PostId) {
-        if let Some(thread) = self.threads.get_mut(&thread_id) {
-            thread.thread_status = ThreadStatus::Expired;
+    /// Files a report against a thread, or one of its replies if `reply_id` is set. Each
+    /// actor reports a post at most once.
+    ///
+    /// Returns whether the post is hidden, which happens once it reaches the report threshold.
+    pub fn report_post(
+        &mut self,
+        thread_id: PostId,
+        reply_id: Option<PostId>,
+        reporter: ActorId,
+        reason: ReportReason,
+    ) -> Result<bool, StorageError> {
+        let thread = self
+            .threads
+            .get_mut(&thread_id)
+            .ok_or(StorageError::ThreadNotFound)?;
+
+        let (post_id, reports, hidden) = match reply_id {
+            None => (thread_id, &mut thread.reports, &mut thread.hidden),
+            Some(reply_id) => {
+                let (_, reply) = thread
+                    .replies
+                    .iter_mut()
+                    .find(|(id, _)| *id == reply_id)
+                    .ok_or(StorageError::ReplyNotFound)?;
+                (reply_id, &mut reply.reports, &mut reply.hidden)
+            }
+        };
+
+        let reported_post = self
+            .reported_posts
+            .entry(post_id)
+            .or_insert_with(|| ReportedPost {
+                thread_id,
+                post_id,
+                reports: Vec::new(),
+            });
+        if reported_post
+            .reports
+            .iter()
+            .any(|report| report.reporter == reporter)
+        {
+            return Err(StorageError::AlreadyReported);
+        }
+        reported_post.reports.push(Report { reporter, reason });
+
+        *reports += 1;
+        if *reports >= self.report_threshold {
+            *hidden = true;
         }
+
+        Ok(*hidden)
+    }
+
+    /// Clears the reports of a thread or reply and makes it visible again.
+    pub fn dismiss_reports(&mut self, post_id: PostId) -> Result<(), StorageError> {
+        let reported_post = self
+            .reported_posts
+            .remove(&post_id)
+            .ok_or(StorageError::NotReported)?;
+        let thread = self
+            .threads
+            .get_mut(&reported_post.thread_id)
+            .ok_or(StorageError::ThreadNotFound)?;
+
+        if post_id == reported_post.thread_id {
+            thread.reports = 0;
+            thread.hidden = false;
+        } else if let Some((_, reply)) = thread.replies.iter_mut().find(|(id, _)| *id == post_id) {
+            reply.reports = 0;
+            reply.hidden = false;
+        }
+
+        Ok(())
+    }
+
+    /// Changes the number of reports hiding a post, which takes at least one report.
+    pub fn set_report_threshold(&mut self, report_threshold: u64) -> Result<(), StorageError> {
+        self.ensure_role(msg::source(), Role::Admin)?;
+        if report_threshold < 1 {
+            return Err(StorageError::InvalidReportThreshold);
+        }
+
+        self.report_threshold = report_threshold;
+        Ok(())
+    }
+
+    pub fn change_status_thread(&mut self, thread_id: PostId) -> Result<(), StorageError> {
+        let thread = self
+            .threads
+            .get_mut(&thread_id)
+            .ok_or(StorageError::ThreadNotFound)?;
+        thread.thread_status = ThreadStatus::Expired;
+
+        Ok(())
     }
 
     pub fn add_logic_contract_address(&mut self, address: ActorId) {
         self.address_logic_contract = Some(address);
     }
 
-    pub fn remove_thread(&mut self, post_id: PostId) {
-        if msg::source() != self.admin.expect("Unable to retrieve admin ActorId") {
-            panic!("Thread may only be removed by admin")
+    pub fn add_reward_logic_contract_address(&mut self, address: ActorId) {
+        self.address_reward_logic_contract = Some(address);
+    }
+
+    pub fn ensure_role(&self, caller: ActorId, role: Role) -> Result<(), StorageError> {
+        self.roles
+            .ensure_role(caller, role)
+            .map_err(StorageError::Roles)
+    }
+
+    /// Checks that `caller` is one of the registered contracts allowed to
+    /// mutate threads, replies and likes.
+    pub fn ensure_authorized_caller(&self, caller: ActorId) -> Result<(), StorageError> {
+        if self.address_logic_contract != Some(caller)
+            && self.address_reward_logic_contract != Some(caller)
+        {
+            return Err(StorageError::Unauthorized);
         }
-        self.threads.remove(&post_id);
+        Ok(())
     }
 
-    pub fn remove_reply(&mut self, thread_id: PostId, reply_id: PostId) {
-        // Check if the caller is the admin
-        let admin_id = self.admin.expect("Admin ActorId must be set");
-        if msg::source() != admin_id {
-            panic!("Reply may only be removed by admin");
+    /// Removes a thread with its replies on behalf of a moderator. An active thread cannot be
+    /// removed: a reported one stays hidden by its reports until it expires.
+    pub fn remove_thread(&mut self, post_id: PostId) -> Result<(), StorageError> {
+        self.ensure_role(msg::source(), Role::Moderator)?;
+        // An active thread still holds its reward pool, it has to be expired and settled first
+        if self.thread(post_id)?.thread_status == ThreadStatus::Active {
+            return Err(StorageError::ThreadActive);
+        }
+
+        self.unindex_thread(post_id);
+        self.replies_by_likes.remove(&post_id);
+        let thread = self
+            .threads
+            .remove(&post_id)
+            .ok_or(StorageError::ThreadNotFound)?;
+        self.deposits.remove(&post_id);
+        self.reported_posts.remove(&post_id);
+
+        if let Some(actor_index) = self.actor_indexes.get_mut(&thread.post_data.owner) {
+            actor_index.threads.remove(&post_id);
         }
+        for (_, reply) in &thread.replies {
+            self.unindex_reply(reply);
+        }
+
+        Ok(())
+    }
+
+    pub fn remove_reply(
+        &mut self,
+        thread_id: PostId,
+        reply_id: PostId,
+    ) -> Result<(), StorageError> {
+        // Check if the caller is a moderator
+        self.ensure_role(msg::source(), Role::Moderator)?;
+        self.delete_reply(thread_id, reply_id)
+    }
+
+    /// Removes a reply from its thread, graph and indexes.
+    fn delete_reply(&mut self, thread_id: PostId, reply_id: PostId) -> Result<(), StorageError> {
+        self.reply(thread_id, reply_id)?;
 
         // Attempt to retrieve the thread and remove the reply
-        if let Some(thread) = self.threads.get_mut(&thread_id) {
-            if let Some(index) = thread.replies.iter().position(|(id, _)| *id == reply_id) {
-                thread.replies.remove(index);
-                // delete reply from graph
-                thread.graph_rep.remove_node(reply_id);
+        self.unindex_thread(thread_id);
+        let thread = self
+            .threads
+            .get_mut(&thread_id)
+            .ok_or(StorageError::ThreadNotFound)?;
+        let index = thread
+            .replies
+            .iter()
+            .position(|(id, _)| *id == reply_id)
+            .ok_or(StorageError::ReplyNotFound)?;
+
+        let (_, reply) = thread.replies.remove(index);
+        // delete reply from graph
+        thread.graph_rep.remove_node(reply_id);
+
+        if let Some(replies_by_likes) = self.replies_by_likes.get_mut(&thread_id) {
+            replies_by_likes.remove(&(reply.likes, Reverse(reply_id)));
+        }
+        self.index_thread(thread_id);
+        self.unindex_reply(&reply);
+
+        Ok(())
+    }
+
+    /// Drops a removed reply from the indexes of its author and likers and from the
+    /// moderation queue.
+    fn unindex_reply(&mut self, reply: &ThreadReply) {
+        let reply_id = reply.post_data.post_id;
+        self.reported_posts.remove(&reply_id);
+
+        if let Some(actor_index) = self.actor_indexes.get_mut(&reply.post_data.owner) {
+            actor_index.replies.remove(&reply_id);
+        }
+        for (liker, _) in &reply.like_history {
+            if let Some(actor_index) = self.actor_indexes.get_mut(liker) {
+                actor_index.likes.remove(&reply_id);
             }
         }
     }
 
-    pub fn get_featured_reply(&self, thread_id: PostId) -> Option<&ThreadReply> {
-        self.threads.get(&thread_id).and_then(|thread| {
-            if thread.replies.is_empty() {
-                None
-            } else {
-                thread
+    /// Takes the thread out of `threads_by_likes` and `threads_by_replies`, before its likes
+    /// or replies change.
+    fn unindex_thread(&mut self, thread_id: PostId) {
+        if let Some(thread) = self.threads.get(&thread_id) {
+            let (likes, replies) = sort_keys(thread);
+            self.threads_by_likes.remove(&(likes, thread_id));
+            self.threads_by_replies.remove(&(replies, thread_id));
+        }
+    }
+
+    /// Puts the thread back into `threads_by_likes` and `threads_by_replies`.
+    fn index_thread(&mut self, thread_id: PostId) {
+        if let Some(thread) = self.threads.get(&thread_id) {
+            let (likes, replies) = sort_keys(thread);
+            self.threads_by_likes.insert((likes, thread_id));
+            self.threads_by_replies.insert((replies, thread_id));
+        }
+    }
+
+    fn reply(&self, thread_id: PostId, reply_id: PostId) -> Result<&ThreadReply, StorageError> {
+        // Replies are kept in the order of their ids
+        let replies = &self.thread(thread_id)?.replies;
+        replies
+            .binary_search_by_key(&reply_id, |(id, _)| *id)
+            .map(|index| &replies[index].1)
+            .map_err(|_| StorageError::ReplyNotFound)
+    }
+
+    /// Whether a thread, or one of its replies if `reply_id` is set, is listed by frontend
+    /// queries. Replies of a hidden thread are hidden with it.
+    fn is_visible(&self, thread_id: PostId, reply_id: Option<PostId>) -> bool {
+        let Ok(thread) = self.thread(thread_id) else {
+            return false;
+        };
+        match reply_id {
+            None => !thread.hidden,
+            Some(reply_id) => {
+                !thread.hidden
+                    && self
+                        .reply(thread_id, reply_id)
+                        .is_ok_and(|reply| !reply.hidden)
+            }
+        }
+    }
+
+    fn thread(&self, thread_id: PostId) -> Result<&Thread, StorageError> {
+        self.threads
+            .get(&thread_id)
+            .ok_or(StorageError::ThreadNotFound)
+    }
+
+    pub fn ensure_thread_active(&self, thread_id: PostId) -> Result<(), StorageError> {
+        match self.thread(thread_id)?.thread_status {
+            ThreadStatus::Active => Ok(()),
+            ThreadStatus::Expired => Err(StorageError::ThreadExpired),
+        }
+    }
+
+    pub fn query(&self, query: StorageQuery) -> Result<StorageQueryReply, StorageError> {
+        let reply = match query {
+            StorageQuery::AllRepliesWithLikes(thread_id) => {
+                let reduced_replies: Vec<(PostId, ActorId, u128)> = self
+                    .thread(thread_id)?
                     .replies
                     .iter()
-                    .min_by_key(|(_, reply)| reply.likes)
+                    .map(|(post_id, reply)| (*post_id, reply.post_data.owner, reply.likes))
+                    .collect();
+
+                StorageQueryReply::AllRepliesWithLikes(reduced_replies)
+            }
+            StorageQuery::Thread(thread_id) => {
+                StorageQueryReply::Thread(QueryThread::from(self.thread(thread_id)?))
+            }
+            StorageQuery::GraphRep(thread_id) => {
+                let graph_rep = &self.thread(thread_id)?.graph_rep;
+
+                StorageQueryReply::GraphRep(graph_rep.clone())
+            }
+            StorageQuery::LikeHistoryOf(thread_id, reply_id) => {
+                let reply = self.reply(thread_id, reply_id)?;
+
+                StorageQueryReply::LikeHistoryOf(reply.like_history.clone())
+            }
+            StorageQuery::AllThreadsFE(threads_query) => {
+                let ThreadsQuery {
+                    filter,
+                    sort,
+                    cursor,
+                    limit,
+                } = threads_query;
+                let visible = |thread: &Thread| !thread.hidden && filter.matches(thread);
+
+                // Read backwards from the cursor, ties are broken by the newest thread
+                let threads = match sort {
+                    ThreadSort::Newest => page_of(
+                        self.threads
+                            .range(below(cursor.map(|cursor| cursor.post_id)))
+                            .rev()
+                            .filter(|(_, thread)| visible(thread))
+                            .map(|(post_id, thread)| {
+                                (
+                                    Cursor {
+                                        key: 0,
+                                        post_id: *post_id,
+                                    },
+                                    thread,
+                                )
+                            }),
+                        limit,
+                    ),
+                    ThreadSort::MostLiked | ThreadSort::MostReplies => {
+                        let index = match sort {
+                            ThreadSort::MostLiked => &self.threads_by_likes,
+                            _ => &self.threads_by_replies,
+                        };
+                        page_of(
+                            index
+                                .range(below(cursor.map(|cursor| (cursor.key, cursor.post_id))))
+                                .rev()
+                                .filter_map(|(key, post_id)| {
+                                    let thread = self.threads.get(post_id)?;
+                                    let cursor = Cursor {
+                                        key: *key,
+                                        post_id: *post_id,
+                                    };
+                                    visible(thread).then_some((cursor, thread))
+                                }),
+                            limit,
+                        )
+                    }
+                };
+
+                let threads_fe = threads.map(|thread| {
+                    let featured_reply_fe = self
+                        .get_featured_reply(thread.post_data.post_id)
+                        .map(|reply| reply.post_data.clone());
+
+                    (QueryThread::from(thread), featured_reply_fe)
+                });
+
+                StorageQueryReply::AllThreadsFE(threads_fe)
+            }
+            StorageQuery::AllRepliesFE(thread_id, replies_query) => {
+                let thread: &Thread = self.thread(thread_id)?;
+
+                let query_thread = QueryThread::from(thread);
+
+                let RepliesQuery {
+                    filter,
+                    sort,
+                    cursor,
+                    limit,
+                } = replies_query;
+                let visible =
+                    |reply: &ThreadReply| !reply.hidden && filter.matches(&reply.post_data);
+
+                // Replies are kept in the order of their ids, ties in likes are broken by the
+                // oldest reply
+                let replies = match sort {
+                    ReplySort::Oldest => {
+                        let start = cursor.map_or(0, |cursor| {
+                            thread
+                                .replies
+                                .partition_point(|(post_id, _)| *post_id <= cursor.post_id)
+                        });
+                        page_of(
+                            thread.replies[start..]
+                                .iter()
+                                .map(by_age)
+                                .filter(|(_, reply)| visible(reply)),
+                            limit,
+                        )
+                    }
+                    ReplySort::Newest => {
+                        let end = cursor.map_or(thread.replies.len(), |cursor| {
+                            thread
+                                .replies
+                                .partition_point(|(post_id, _)| *post_id < cursor.post_id)
+                        });
+                        page_of(
+                            thread.replies[..end]
+                                .iter()
+                                .rev()
+                                .map(by_age)
+                                .filter(|(_, reply)| visible(reply)),
+                            limit,
+                        )
+                    }
+                    ReplySort::MostLiked => page_of(
+                        self.replies_by_likes
+                            .get(&thread_id)
+                            .ok_or(StorageError::ThreadNotFound)?
+                            .range(below(
+                                cursor.map(|cursor| (cursor.key, Reverse(cursor.post_id))),
+                            ))
+                            .rev()
+                            .filter_map(|(likes, Reverse(post_id))| {
+                                let reply = self.reply(thread_id, *post_id).ok()?;
+                                let cursor = Cursor {
+                                    key: *likes,
+                                    post_id: *post_id,
+                                };
+                                visible(reply).then_some((cursor, reply))
+                            }),
+                        limit,
+                    ),
+                };
+
+                let replies_fe = replies.map(|thread_reply| QueryReply {
+                    post_data: thread_reply.post_data.clone(),
+                    thread_id: thread_reply.thread_id,
+                });
+
+                StorageQueryReply::AllRepliesFE(query_thread, replies_fe)
+            }
+            StorageQuery::ThreadsOf(actor_id, cursor, limit) => {
+                let thread_ids = self
+                    .actor_indexes
+                    .get(&actor_id)
+                    .map(|actor_index| {
+                        page_of(
+                            actor_index
+                                .threads
+                                .range(below(cursor))
+                                .rev()
+                                .filter(|thread_id| self.is_visible(**thread_id, None))
+                                .map(|thread_id| (*thread_id, *thread_id)),
+                            limit,
+                        )
+                    })
+                    .unwrap_or_default();
+
+                let mut threads = Vec::with_capacity(thread_ids.items.len());
+                for thread_id in &thread_ids.items {
+                    threads.push(QueryThread::from(self.thread(*thread_id)?));
+                }
+
+                StorageQueryReply::ThreadsOf(Page {
+                    items: threads,
+                    next_cursor: thread_ids.next_cursor,
+                })
+            }
+            StorageQuery::RepliesOf(actor_id, cursor, limit) => {
+                let reply_ids = self
+                    .actor_indexes
+                    .get(&actor_id)
+                    .map(|actor_index| {
+                        page_of(
+                            actor_index
+                                .replies
+                                .range(below(cursor))
+                                .rev()
+                                .filter(|(reply_id, thread_id)| {
+                                    self.is_visible(**thread_id, Some(**reply_id))
+                                })
+                                .map(|(reply_id, thread_id)| (*reply_id, (*thread_id, *reply_id))),
+                            limit,
+                        )
+                    })
+                    .unwrap_or_default();
+
+                let mut replies = Vec::with_capacity(reply_ids.items.len());
+                for (thread_id, reply_id) in &reply_ids.items {
+                    let reply = self.reply(*thread_id, *reply_id)?;
+                    replies.push(QueryReply {
+                        post_data: reply.post_data.clone(),
+                        thread_id: *thread_id,
+                    });
+                }
+
+                StorageQueryReply::RepliesOf(Page {
+                    items: replies,
+                    next_cursor: reply_ids.next_cursor,
+                })
+            }
+            StorageQuery::LikesOf(actor_id, cursor, limit) => {
+                let liked_ids = self
+                    .actor_indexes
+                    .get(&actor_id)
+                    .map(|actor_index| {
+                        page_of(
+                            actor_index
+                                .likes
+                                .range(below(cursor))
+                                .rev()
+                                .filter(|(reply_id, thread_id)| {
+                                    self.is_visible(**thread_id, Some(**reply_id))
+                                })
+                                .map(|(reply_id, thread_id)| (*reply_id, (*thread_id, *reply_id))),
+                            limit,
+                        )
+                    })
+                    .unwrap_or_default();
+
+                let mut likes = Vec::with_capacity(liked_ids.items.len());
+                for (thread_id, reply_id) in &liked_ids.items {
+                    let likes_given = self
+                        .reply(*thread_id, *reply_id)?
+                        .like_history
+                        .iter()
+                        .find(|(liker, _)| *liker == actor_id)
+                        .map_or(0, |(_, likes_given)| *likes_given);
+                    likes.push(LikeActivity {
+                        thread_id: *thread_id,
+                        reply_id: *reply_id,
+                        likes_given,
+                    });
+                }
+
+                StorageQueryReply::LikesOf(Page {
+                    items: likes,
+                    next_cursor: liked_ids.next_cursor,
+                })
+            }
+            StorageQuery::ModerationQueue(cursor, limit) => {
+                let after_cursor = (
+                    cursor.map_or(Bound::Unbounded, Bound::Excluded),
+                    Bound::Unbounded,
+                );
+                let reported_posts = page_of(
+                    self.reported_posts
+                        .range(after_cursor)
+                        .map(|(post_id, reported_post)| (*post_id, reported_post.clone())),
+                    limit,
+                );
+
+                StorageQueryReply::ModerationQueue(reported_posts)
+            }
+            StorageQuery::DistributedTokens(thread_id) => {
+                let distributed_tokens: u128 = self.thread(thread_id)?.distributed_tokens;
+
+                StorageQueryReply::DistributedTokens(distributed_tokens)
+            }
+            StorageQuery::DepositLedger(thread_id) => {
+                let deposits: Vec<Deposit> = self
+                    .deposits
+                    .get(&thread_id)
+                    .cloned()
+                    .ok_or(StorageError::ThreadNotFound)?;
+
+                StorageQueryReply::DepositLedger(deposits)
+            }
+        };
+
+        Ok(reply)
+    }
+
+    /// Selects the reply to feature for the thread according to its `FeaturedReply` mode.
+    /// Hidden replies are never featured, and a pinned or accepted reply that has since
+    /// been removed or hidden falls back to the most liked reply.
+    pub fn get_featured_reply(&self, thread_id: PostId) -> Option<&ThreadReply> {
+        let thread = self.threads.get(&thread_id)?;
+
+        let visible_replies = move || thread.replies.iter().filter(|(_, reply)| !reply.hidden);
+        let most_liked = move || {
+            visible_replies()
+                .max_by(|(id_a, reply_a), (id_b, reply_b)| {
+                    reply_a.likes.cmp(&reply_b.likes).then(id_b.cmp(id_a))
+                })
+                .map(|(_, reply)| reply)
+        };
+
+        match thread.featured_reply {
+            FeaturedReply::MostLiked => most_liked(),
+            FeaturedReply::Newest => visible_replies()
+                .max_by_key(|(id, _)| *id)
+                .map(|(_, reply)| reply),
+            FeaturedReply::Pinned(reply_id) | FeaturedReply::Accepted(reply_id) => {
+                visible_replies()
+                    .find(|(id, _)| *id == reply_id)
                     .map(|(_, reply)| reply)
+                    .or_else(most_liked)
             }
-        })
+        }
+    }
+}
+
+impl Default for ThreadStorage {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
+/// Configuration of a new storage contract. Addresses left out can be set later with the
+/// corresponding actions.
+#[derive(Default, Encode, Decode, TypeInfo)]
+#[codec(crate = gstd::codec)]
+#[scale_info(crate = gstd::scale_info)]
+pub struct InitStorage {
+    // Owner of the contract roles, defaults to the deployer
+    pub owner: Option<ActorId>,
+    pub address_logic_contract: Option<ActorId>,
+    pub address_reward_logic_contract: Option<ActorId>,
+}
+
 #[derive(Encode, Decode, TypeInfo)]
 #[codec(crate = gstd::codec)]
 #[scale_info(crate = gstd::scale_info)]
 pub enum StorageAction {
+    GrantRole(Role, ActorId),
+    RevokeRole(Role, ActorId),
+    // First step of an ownership transfer, completed by the new owner with `AcceptOwnership`
+    TransferOwnership(ActorId),
+    AcceptOwnership,
     AddLogicContractAddress(ActorId),
+    AddRewardLogicContractAddress(ActorId),
     PushThread(Thread),
     PushReply(PostId, ThreadReply, PostId),
-    LikeReply(PostId, PostId, u128),
+    // Thread id, reply id, liker and like count
+    LikeReply(PostId, PostId, ActorId, u128),
     ChangeStatusState(PostId),
+    SetFeaturedReply(PostId, FeaturedReply),
+    // Thread id, reporter and reason
+    ReportThread(PostId, ActorId, ReportReason),
+    // Thread id, reply id, reporter and reason
+    ReportReply(PostId, PostId, ActorId, ReportReason),
+    // Moderator only, clears the reports of a thread or reply
+    DismissReports(PostId),
+    // Admin only, number of reports hiding a post
+    SetReportThreshold(u64),
+    // Moderator only, refused with `ThreadActive` until the thread has expired
     RemoveThread(PostId),
     RemoveReply(PostId, PostId),
+    // Read-only query for other contracts, replied to with a `StorageQueryReply` result
+    Query(StorageQuery),
 }
 
 #[derive(Encode, Decode, TypeInfo)]
 #[codec(crate = gstd::codec)]
 #[scale_info(crate = gstd::scale_info)]
 pub enum StorageEvent {
+    RoleGranted,
+    RoleRevoked,
+    OwnershipTransferStarted,
+    OwnershipTransferred,
     LogicContractAddressAdded,
-    StorageError,
+    RewardLogicContractAddressAdded,
     ThreadPush(PostId),
     ReplyPush(PostId),
     ReplyLiked,
     StatusStateChanged,
+    FeaturedReplySet,
+    // Whether the reported post is now hidden
+    PostReported(bool),
+    ReportsDismissed,
+    ReportThresholdSet,
     ThreadRemoved,
     ReplyRemoved,
 }
 
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, PartialEq, Eq)]
+#[codec(crate = gstd::codec)]
+#[scale_info(crate = gstd::scale_info)]
+pub enum StorageError {
+    Roles(RolesError),
+    // Sender is not a registered logic or reward logic contract
+    Unauthorized,
+    ThreadNotFound,
+    ReplyNotFound,
+    // Thread no longer accepts replies or likes
+    ThreadExpired,
+    // Thread still holds its reward pool and cannot be removed
+    ThreadActive,
+    // Only question threads have an accepted answer
+    NotAQuestion,
+    // Reply references a post that is not part of the thread
+    RefNodeNotFound,
+    // Actor has already reported the post
+    AlreadyReported,
+    // Post is not in the moderation queue
+    NotReported,
+    // Posts are hidden after at least one report
+    InvalidReportThreshold,
+    // A like gives at least one token
+    InvalidLikeCount,
+}
+
 #[derive(Encode, Decode, TypeInfo)]
 #[codec(crate = gstd::codec)]
 #[scale_info(crate = gstd::scale_info)]
 pub enum StorageQuery {
+    // Fetch a single thread with its type and status
+    Thread(PostId),
     // For winner (rule no. 1)
     AllRepliesWithLikes(PostId),
     // For path to the winner (rule no. 2)
     GraphRep(PostId),
     // For top liker of winner (rule no. 3)
     LikeHistoryOf(PostId, PostId),
-    // Fetch all threads with the title, content, owner and a single reply
-    AllThreadsFE,
-    // Fetch all replies for a given thread in a post_data format
-    AllRepliesFE(PostId),
+    // Fetch a page of threads with the title, content, owner and the featured reply
+    AllThreadsFE(ThreadsQuery),
+    // Fetch a page of replies for a given thread in a post_data format
+    AllRepliesFE(PostId, RepliesQuery),
+    // Threads, replies and likes of an actor, newest first, after the cursor and up to
+    // the limit
+    ThreadsOf(ActorId, Option<PostId>, u32),
+    RepliesOf(ActorId, Option<PostId>, u32),
+    LikesOf(ActorId, Option<PostId>, u32),
+    // Reported threads and replies, oldest first, after the cursor and up to the limit.
+    // Reported threads can only be hidden or dismissed until they expire, then removed
+    ModerationQueue(Option<PostId>, u32),
     // Fetch the distributed tokens for a given thread
     DistributedTokens(PostId),
+    // Fetch the deposits made into a given thread's reward pool
+    DepositLedger(PostId),
 }
 
 #[derive(Encode, Decode, TypeInfo)]
 #[codec(crate = gstd::codec)]
 #[scale_info(crate = gstd::scale_info)]
 pub enum StorageQueryReply {
+    // Fetch a single thread with its type and status
+    Thread(QueryThread),
     // For winner (rule no. 1)
     AllRepliesWithLikes(Vec<(PostId, ActorId, u128)>),
     // For path to the winner (rule no. 2)
     GraphRep(ThreadGraph),
     // For top liker of winner (rule no. 3)
     LikeHistoryOf(Vec<(ActorId, u128)>),
-    // Fetch all threads with the title, content, owner and a single reply
-    AllThreadsFE(Vec<(QueryThread, Option<Post>)>),
-    // Fetch all replies and the thread itself for a given thread in a post_data format
-    AllRepliesFE(QueryThread, Vec<QueryReply>),
+    // Fetch a page of threads with the title, content, owner and the featured reply
+    AllThreadsFE(Page<(QueryThread, Option<Post>), Cursor>),
+    // Fetch a page of replies and the thread itself for a given thread in a post_data format
+    AllRepliesFE(QueryThread, Page<QueryReply, Cursor>),
+    ThreadsOf(Page<QueryThread>),
+    RepliesOf(Page<QueryReply>),
+    LikesOf(Page<LikeActivity>),
+    ModerationQueue(Page<ReportedPost>),
     DistributedTokens(u128),
+    // Deposits made into the thread's reward pool, in order
+    DepositLedger(Vec<Deposit>),
 }
 
 pub struct ContractMetadata;
 
 impl Metadata for ContractMetadata {
-    type Init = ();
-    type Handle = InOut<StorageAction, StorageEvent>;
+    type Init = In<InitStorage>;
+    type Handle = InOut<StorageAction, Result<StorageEvent, StorageError>>;
     type Reply = ();
     type Others = ();
     type Signal = ();
-    type State = InOut<StorageQuery, StorageQueryReply>;
+    type State = InOut<StorageQuery, Result<StorageQueryReply, StorageError>>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn post(post_id: PostId, owner: u64, posted_at: Timestamp) -> Post {
+        Post {
+            post_id,
+            posted_at,
+            owner: ActorId::from(owner),
+            title: String::new(),
+            content: String::new(),
+            photo_url: None,
+        }
+    }
+
+    fn thread(post_data: Post, thread_type: ThreadType) -> Thread {
+        Thread {
+            post_data,
+            replies: Vec::new(),
+            thread_status: ThreadStatus::Active,
+            thread_type,
+            distributed_tokens: 0,
+            graph_rep: ThreadGraph::new(),
+            featured_reply: FeaturedReply::MostLiked,
+            reports: 0,
+            hidden: false,
+        }
+    }
+
+    fn reply(post_data: Post, thread_id: PostId, likes: u128) -> ThreadReply {
+        ThreadReply {
+            post_data,
+            likes,
+            reports: 0,
+            like_history: Vec::new(),
+            thread_id,
+            hidden: false,
+        }
+    }
+
+    fn thread_ids(
+        thread_storage: &ThreadStorage,
+        threads_query: ThreadsQuery,
+    ) -> Page<PostId, Cursor> {
+        match thread_storage.query(StorageQuery::AllThreadsFE(threads_query)) {
+            Ok(StorageQueryReply::AllThreadsFE(page)) => {
+                page.map(|(query_thread, _)| query_thread.post_data.post_id)
+            }
+            _ => panic!("Unexpected storage reply"),
+        }
+    }
+
+    // Threads 1, 2 and 3 with 0, 2 and 1 replies, thread 3 being a question
+    fn thread_storage() -> ThreadStorage {
+        let mut thread_storage = ThreadStorage::new();
+        thread_storage.push_thread(thread(post(0, 1, 100), ThreadType::Challenge));
+        let thread_id = thread_storage.push_thread(thread(post(0, 2, 200), ThreadType::Challenge));
+        let question_id = thread_storage.push_thread(thread(post(0, 1, 300), ThreadType::Question));
+        for (thread_id, likes) in [(thread_id, 1), (thread_id, 2), (question_id, 5)] {
+            thread_storage
+                .push_reply(
+                    thread_id,
+                    reply(post(0, 3, 400), thread_id, likes),
+                    thread_id,
+                )
+                .expect("Thread is active");
+        }
+        thread_storage
+    }
+
+    #[test]
+    fn threads_are_paginated_with_cursor() {
+        let thread_storage = thread_storage();
+
+        let threads_query = ThreadsQuery {
+            limit: 2,
+            ..Default::default()
+        };
+        let first_page = thread_ids(&thread_storage, threads_query.clone());
+        assert_eq!(first_page.items, vec![3, 2]);
+        assert_eq!(first_page.next_cursor, Some(Cursor { key: 0, post_id: 2 }));
+
+        let second_page = thread_ids(
+            &thread_storage,
+            ThreadsQuery {
+                cursor: first_page.next_cursor,
+                ..threads_query
+            },
+        );
+        assert_eq!(second_page.items, vec![1]);
+        assert_eq!(second_page.next_cursor, None);
+    }
+
+    #[test]
+    fn threads_are_sorted() {
+        let thread_storage = thread_storage();
+
+        let most_liked = thread_ids(
+            &thread_storage,
+            ThreadsQuery {
+                sort: ThreadSort::MostLiked,
+                limit: MAX_PAGE_SIZE,
+                ..Default::default()
+            },
+        );
+        assert_eq!(most_liked.items, vec![3, 2, 1]);
+
+        let most_replies = thread_ids(
+            &thread_storage,
+            ThreadsQuery {
+                sort: ThreadSort::MostReplies,
+                limit: MAX_PAGE_SIZE,
+                ..Default::default()
+            },
+        );
+        assert_eq!(most_replies.items, vec![2, 3, 1]);
+    }
+
+    #[test]
+    fn threads_are_filtered() {
+        let thread_storage = thread_storage();
+
+        let questions = thread_ids(
+            &thread_storage,
+            ThreadsQuery {
+                filter: ThreadFilter {
+                    thread_type: Some(ThreadType::Question),
+                    ..Default::default()
+                },
+                limit: MAX_PAGE_SIZE,
+                ..Default::default()
+            },
+        );
+        assert_eq!(questions.items, vec![3]);
+
+        let owned_before = thread_ids(
+            &thread_storage,
+            ThreadsQuery {
+                filter: ThreadFilter {
+                    post: PostFilter {
+                        owner: Some(ActorId::from(1)),
+                        posted_to: Some(200),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                limit: MAX_PAGE_SIZE,
+                ..Default::default()
+            },
+        );
+        assert_eq!(owned_before.items, vec![1]);
+    }
+
+    #[test]
+    fn replies_are_sorted_by_likes() {
+        let thread_storage = thread_storage();
+
+        let reply = thread_storage.query(StorageQuery::AllRepliesFE(
+            2,
+            RepliesQuery {
+                sort: ReplySort::MostLiked,
+                limit: MAX_PAGE_SIZE,
+                ..Default::default()
+            },
+        ));
+        let Ok(StorageQueryReply::AllRepliesFE(_, page)) = reply else {
+            panic!("Unexpected storage reply");
+        };
+        let reply_ids: Vec<PostId> = page
+            .items
+            .iter()
+            .map(|query_reply| query_reply.post_data.post_id)
+            .collect();
+        assert_eq!(reply_ids, vec![5, 4]);
+    }
+
+    #[test]
+    fn like_without_tokens_is_rejected() {
+        let mut thread_storage = thread_storage();
+
+        assert_eq!(
+            thread_storage.like_reply(2, 5, ActorId::from(9), 0),
+            Err(StorageError::InvalidLikeCount)
+        );
+        let reply = thread_storage.reply(2, 5).expect("Reply exists");
+        assert_eq!(reply.likes, 2);
+        assert!(reply.like_history.is_empty());
+        assert_eq!(thread_storage.deposits[&2].len(), 3);
+        assert!(!thread_storage.actor_indexes.contains_key(&ActorId::from(9)));
+    }
+
+    #[test]
+    fn actor_posts_and_likes_are_indexed() {
+        let mut thread_storage = thread_storage();
+        thread_storage
+            .like_reply(2, 5, ActorId::from(1), 3)
+            .expect("Reply exists");
+
+        let first_page = thread_storage.query(StorageQuery::ThreadsOf(ActorId::from(1), None, 1));
+        let Ok(StorageQueryReply::ThreadsOf(first_page)) = first_page else {
+            panic!("Unexpected storage reply");
+        };
+        assert_eq!(first_page.items[0].post_data.post_id, 3);
+        assert_eq!(first_page.next_cursor, Some(3));
+
+        let second_page = thread_storage.query(StorageQuery::ThreadsOf(
+            ActorId::from(1),
+            first_page.next_cursor,
+            1,
+        ));
+        let Ok(StorageQueryReply::ThreadsOf(second_page)) = second_page else {
+            panic!("Unexpected storage reply");
+        };
+        assert_eq!(second_page.items[0].post_data.post_id, 1);
+        assert_eq!(second_page.next_cursor, None);
+
+        let replies = thread_storage.query(StorageQuery::RepliesOf(
+            ActorId::from(3),
+            None,
+            MAX_PAGE_SIZE,
+        ));
+        let Ok(StorageQueryReply::RepliesOf(replies)) = replies else {
+            panic!("Unexpected storage reply");
+        };
+        let reply_ids: Vec<(PostId, PostId)> = replies
+            .items
+            .iter()
+            .map(|query_reply| (query_reply.thread_id, query_reply.post_data.post_id))
+            .collect();
+        assert_eq!(reply_ids, vec![(3, 6), (2, 5), (2, 4)]);
+
+        let likes =
+            thread_storage.query(StorageQuery::LikesOf(ActorId::from(1), None, MAX_PAGE_SIZE));
+        let Ok(StorageQueryReply::LikesOf(likes)) = likes else {
+            panic!("Unexpected storage reply");
+        };
+        assert_eq!(likes.items.len(), 1);
+        assert_eq!(
+            (
+                likes.items[0].thread_id,
+                likes.items[0].reply_id,
+                likes.items[0].likes_given
+            ),
+            (2, 5, 3)
+        );
+    }
+
+    #[test]
+    fn actor_queries_skip_hidden_posts() {
+        let mut thread_storage = thread_storage();
+        thread_storage
+            .like_reply(2, 5, ActorId::from(1), 3)
+            .expect("Reply exists");
+        thread_storage
+            .threads
+            .get_mut(&3)
+            .expect("Thread exists")
+            .hidden = true;
+        thread_storage
+            .threads
+            .get_mut(&2)
+            .and_then(|thread| thread.replies.iter_mut().find(|(id, _)| *id == 5))
+            .expect("Reply exists")
+            .1
+            .hidden = true;
+
+        let threads = thread_storage.query(StorageQuery::ThreadsOf(
+            ActorId::from(1),
+            None,
+            MAX_PAGE_SIZE,
+        ));
+        let Ok(StorageQueryReply::ThreadsOf(threads)) = threads else {
+            panic!("Unexpected storage reply");
+        };
+        assert_eq!(threads.items.len(), 1);
+        assert_eq!(threads.items[0].post_data.post_id, 1);
+
+        // Reply 6 belongs to the hidden thread 3, reply 5 is hidden itself
+        let replies = thread_storage.query(StorageQuery::RepliesOf(
+            ActorId::from(3),
+            None,
+            MAX_PAGE_SIZE,
+        ));
+        let Ok(StorageQueryReply::RepliesOf(replies)) = replies else {
+            panic!("Unexpected storage reply");
+        };
+        assert_eq!(replies.items.len(), 1);
+        assert_eq!(replies.items[0].post_data.post_id, 4);
+
+        let likes =
+            thread_storage.query(StorageQuery::LikesOf(ActorId::from(1), None, MAX_PAGE_SIZE));
+        let Ok(StorageQueryReply::LikesOf(likes)) = likes else {
+            panic!("Unexpected storage reply");
+        };
+        assert!(likes.items.is_empty());
+    }
+
+    #[test]
+    fn default_storage_is_ready_for_posts() {
+        let thread_storage = ThreadStorage::default();
+
+        assert_eq!(thread_storage.next_post_id, 1);
+        assert_eq!(thread_storage.report_threshold, DEFAULT_REPORT_THRESHOLD);
+    }
+
+    #[test]
+    fn nested_replies_are_part_of_thread_graph() {
+        let mut thread_storage = thread_storage();
+
+        let nested_id = thread_storage
+            .push_reply(2, reply(post(0, 4, 500), 2, 0), 5)
+            .expect("Referenced reply is part of the thread");
+
+        let graph_rep = &thread_storage.threads[&2].graph_rep;
+        let path: Vec<PostId> = graph_rep
+            .path(2, nested_id)
+            .expect("Nested reply is reachable from the thread")
+            .iter()
+            .map(|(post_id, _)| *post_id)
+            .collect();
+        assert_eq!(path, vec![2, 5, nested_id]);
+    }
+
+    #[test]
+    fn reply_to_post_of_other_thread_is_rejected() {
+        let mut thread_storage = thread_storage();
+
+        // Reply 6 belongs to thread 3
+        let res = thread_storage.push_reply(2, reply(post(0, 4, 500), 2, 0), 6);
+
+        assert_eq!(res, Err(StorageError::RefNodeNotFound));
+        assert_eq!(thread_storage.next_post_id, 7);
+    }
+
+    #[test]
+    fn reported_reply_is_hidden_at_threshold() {
+        let mut thread_storage = thread_storage();
+
+        for reporter in 1..DEFAULT_REPORT_THRESHOLD {
+            let hidden =
+                thread_storage.report_post(2, Some(5), ActorId::from(reporter), ReportReason::Spam);
+            assert_eq!(hidden, Ok(false));
+        }
+        assert_eq!(
+            thread_storage.report_post(2, Some(5), ActorId::from(1), ReportReason::Abuse),
+            Err(StorageError::AlreadyReported)
+        );
+        let hidden = thread_storage.report_post(
+            2,
+            Some(5),
+            ActorId::from(DEFAULT_REPORT_THRESHOLD),
+            ReportReason::Spam,
+        );
+        assert_eq!(hidden, Ok(true));
+
+        // The hidden reply is no longer listed nor featured
+        let reply = thread_storage.query(StorageQuery::AllRepliesFE(2, RepliesQuery::default()));
+        let Ok(StorageQueryReply::AllRepliesFE(_, replies)) = reply else {
+            panic!("Unexpected storage reply");
+        };
+        assert_eq!(replies.items.len(), 1);
+        assert_eq!(
+            thread_storage
+                .get_featured_reply(2)
+                .map(|reply| reply.post_data.post_id),
+            Some(4)
+        );
+
+        let queue = thread_storage.query(StorageQuery::ModerationQueue(None, MAX_PAGE_SIZE));
+        let Ok(StorageQueryReply::ModerationQueue(queue)) = queue else {
+            panic!("Unexpected storage reply");
+        };
+        assert_eq!(queue.items.len(), 1);
+        assert_eq!(queue.items[0].post_id, 5);
+        assert_eq!(
+            queue.items[0].reports.len() as u64,
+            DEFAULT_REPORT_THRESHOLD
+        );
+
+        thread_storage
+            .dismiss_reports(5)
+            .expect("Reply is in the moderation queue");
+        assert!(!thread_storage.reply(2, 5).expect("Reply exists").hidden);
+        assert!(thread_storage.reported_posts.is_empty());
+    }
+
+    fn reply_ids(
+        thread_storage: &ThreadStorage,
+        replies_query: RepliesQuery,
+    ) -> Page<PostId, Cursor> {
+        match thread_storage.query(StorageQuery::AllRepliesFE(2, replies_query)) {
+            Ok(StorageQueryReply::AllRepliesFE(_, page)) => {
+                page.map(|query_reply| query_reply.post_data.post_id)
+            }
+            _ => panic!("Unexpected storage reply"),
+        }
+    }
+
+    #[test]
+    fn cursor_outlives_its_hidden_thread() {
+        let mut thread_storage = thread_storage();
+
+        let threads_query = ThreadsQuery {
+            limit: 2,
+            ..Default::default()
+        };
+        let first_page = thread_ids(&thread_storage, threads_query.clone());
+        assert_eq!(first_page.items, vec![3, 2]);
+
+        // The cursor thread disappears and a newer thread shows up between the pages
+        thread_storage
+            .threads
+            .get_mut(&2)
+            .expect("Thread exists")
+            .hidden = true;
+        thread_storage.push_thread(thread(post(0, 1, 500), ThreadType::Challenge));
+
+        let second_page = thread_ids(
+            &thread_storage,
+            ThreadsQuery {
+                cursor: first_page.next_cursor,
+                ..threads_query
+            },
+        );
+        assert_eq!(second_page.items, vec![1]);
+        assert_eq!(second_page.next_cursor, None);
+    }
+
+    #[test]
+    fn most_liked_pages_follow_likes_given_between_pages() {
+        let mut thread_storage = thread_storage();
+
+        // Threads 3, 2 and 1 have 5, 3 and 0 likes
+        let threads_query = ThreadsQuery {
+            sort: ThreadSort::MostLiked,
+            limit: 1,
+            ..Default::default()
+        };
+        let first_page = thread_ids(&thread_storage, threads_query.clone());
+        assert_eq!(first_page.items, vec![3]);
+        assert_eq!(first_page.next_cursor, Some(Cursor { key: 5, post_id: 3 }));
+
+        // Thread 3 moves further ahead and thread 2 catches up with the cursor, neither
+        // is returned twice nor skipped
+        thread_storage
+            .like_reply(3, 6, ActorId::from(1), 10)
+            .expect("Reply exists");
+        thread_storage
+            .like_reply(2, 4, ActorId::from(1), 2)
+            .expect("Reply exists");
+
+        let second_page = thread_ids(
+            &thread_storage,
+            ThreadsQuery {
+                cursor: first_page.next_cursor,
+                limit: MAX_PAGE_SIZE,
+                ..threads_query
+            },
+        );
+        assert_eq!(second_page.items, vec![2, 1]);
+    }
+
+    #[test]
+    fn reply_pages_resume_after_removed_cursor_reply() {
+        let mut thread_storage = thread_storage();
+
+        // Replies 5 and 4 of thread 2 have 2 and 1 likes
+        let replies_query = RepliesQuery {
+            sort: ReplySort::MostLiked,
+            limit: 1,
+            ..Default::default()
+        };
+        let first_page = reply_ids(&thread_storage, replies_query.clone());
+        assert_eq!(first_page.items, vec![5]);
+
+        thread_storage.delete_reply(2, 5).expect("Reply exists");
+        let new_reply_id = thread_storage
+            .push_reply(2, reply(post(0, 4, 500), 2, 0), 2)
+            .expect("Thread is active");
+
+        let second_page = reply_ids(
+            &thread_storage,
+            RepliesQuery {
+                cursor: first_page.next_cursor,
+                limit: MAX_PAGE_SIZE,
+                ..replies_query
+            },
+        );
+        assert_eq!(second_page.items, vec![4, new_reply_id]);
+
+        // Pages by age resume from the position of the cursor reply as well
+        let newest = reply_ids(
+            &thread_storage,
+            RepliesQuery {
+                sort: ReplySort::Newest,
+                cursor: Some(Cursor { key: 0, post_id: 5 }),
+                limit: MAX_PAGE_SIZE,
+                ..Default::default()
+            },
+        );
+        assert_eq!(newest.items, vec![4]);
+    }
 }
@@ -1,10 +1,9 @@
 #![no_std]
 
-use gstd::{msg, prelude::*, ActorId};
-use io::{Post, PostId, Thread};
+use gstd::msg;
+use io::roles::{Role, Roles};
 use storage_io::{
-    QueryReply, QueryThread, StorageAction, StorageEvent, StorageQuery, StorageQueryReply,
-    ThreadStorage,
+    InitStorage, StorageAction, StorageError, StorageEvent, StorageQuery, ThreadStorage,
 };
 
 static mut THREAD_STORAGE: Option<ThreadStorage> = None;
@@ -15,11 +14,16 @@ fn thread_storage_mut() -> &'static mut ThreadStorage {
 
 #[no_mangle]
 extern fn init() {
-    let thread_storage = ThreadStorage::new();
+    let init: InitStorage = msg::load().expect("Could not load InitStorage");
 
-    unsafe { THREAD_STORAGE = Some(thread_storage) }
+    let thread_storage = ThreadStorage {
+        roles: Roles::new(init.owner.unwrap_or_else(msg::source)),
+        address_logic_contract: init.address_logic_contract,
+        address_reward_logic_contract: init.address_reward_logic_contract,
+        ..ThreadStorage::new()
+    };
 
-    thread_storage_mut().admin = Some(msg::source());
+    unsafe { THREAD_STORAGE = Some(thread_storage) }
 }
 
 #[no_mangle]
@@ -27,146 +31,94 @@ extern fn handle() {
     let action: StorageAction = msg::load().expect("Could not load Action");
     let thread_storage = thread_storage_mut();
 
-    match action {
-        StorageAction::AddLogicContractAddress(address) => {
-            if thread_storage.admin.expect("") != msg::source() {
-                panic!("AddLogicContractAddress action can only be called by admin")
-            }
-            thread_storage.add_logic_contract_address(address);
-            msg::reply(StorageEvent::LogicContractAddressAdded, 0)
-                .expect("Failed to reply to AddLogicContractAddress Action");
-        }
-        StorageAction::PushThread(thread) => {
-            let thread_id = thread.post_data.post_id;
-            thread_storage.push_thread(thread);
-            msg::reply(StorageEvent::ThreadPush(thread_id), 0)
-                .expect("Failed to reply to PushThread Action");
-        }
-        StorageAction::PushReply(thread_id, reply, ref_node) => {
-            let reply_id = reply.post_data.post_id;
-            thread_storage.push_reply(thread_id, reply, ref_node);
-            msg::reply(StorageEvent::ReplyPush(reply_id), 0)
-                .expect("Failed to reply to PushReply Action");
-        }
-        StorageAction::LikeReply(thread_id, reply_id, like_count) => {
-            thread_storage.like_reply(thread_id, reply_id, like_count);
-            msg::reply(StorageEvent::ReplyLiked, 0).expect("Failed to reply to LikeReply Action");
-        }
-        StorageAction::ChangeStatusState(thread_id) => {
-            thread_storage.change_status_thread(thread_id);
-            msg::reply(StorageEvent::StatusStateChanged, 0)
-                .expect("Failed to reply to ChangeStatusState Action");
+    let result = match action {
+        StorageAction::GrantRole(role, actor) => thread_storage
+            .roles
+            .grant(msg::source(), role, actor)
+            .map_err(StorageError::Roles)
+            .map(|()| StorageEvent::RoleGranted),
+        StorageAction::RevokeRole(role, actor) => thread_storage
+            .roles
+            .revoke(msg::source(), role, actor)
+            .map_err(StorageError::Roles)
+            .map(|()| StorageEvent::RoleRevoked),
+        StorageAction::TransferOwnership(new_owner) => thread_storage
+            .roles
+            .transfer_ownership(msg::source(), new_owner)
+            .map_err(StorageError::Roles)
+            .map(|()| StorageEvent::OwnershipTransferStarted),
+        StorageAction::AcceptOwnership => thread_storage
+            .roles
+            .accept_ownership(msg::source())
+            .map_err(StorageError::Roles)
+            .map(|()| StorageEvent::OwnershipTransferred),
+        StorageAction::AddLogicContractAddress(address) => thread_storage
+            .ensure_role(msg::source(), Role::Admin)
+            .map(|()| {
+                thread_storage.add_logic_contract_address(address);
+                StorageEvent::LogicContractAddressAdded
+            }),
+        StorageAction::AddRewardLogicContractAddress(address) => thread_storage
+            .ensure_role(msg::source(), Role::Admin)
+            .map(|()| {
+                thread_storage.add_reward_logic_contract_address(address);
+                StorageEvent::RewardLogicContractAddressAdded
+            }),
+        // Threads, replies, likes and statuses may only be mutated by the registered contracts
+        StorageAction::PushThread(thread) => thread_storage
+            .ensure_authorized_caller(msg::source())
+            .map(|()| StorageEvent::ThreadPush(thread_storage.push_thread(thread))),
+        StorageAction::PushReply(thread_id, reply, ref_node) => thread_storage
+            .ensure_authorized_caller(msg::source())
+            .and_then(|()| thread_storage.push_reply(thread_id, reply, ref_node))
+            .map(StorageEvent::ReplyPush),
+        StorageAction::LikeReply(thread_id, reply_id, liker, like_count) => thread_storage
+            .ensure_authorized_caller(msg::source())
+            .and_then(|()| thread_storage.like_reply(thread_id, reply_id, liker, like_count))
+            .map(|()| StorageEvent::ReplyLiked),
+        StorageAction::ChangeStatusState(thread_id) => thread_storage
+            .ensure_authorized_caller(msg::source())
+            .and_then(|()| thread_storage.change_status_thread(thread_id))
+            .map(|()| StorageEvent::StatusStateChanged),
+        StorageAction::SetFeaturedReply(thread_id, featured_reply) => thread_storage
+            .ensure_authorized_caller(msg::source())
+            .and_then(|()| thread_storage.set_featured_reply(thread_id, featured_reply))
+            .map(|()| StorageEvent::FeaturedReplySet),
+        StorageAction::ReportThread(thread_id, reporter, reason) => thread_storage
+            .ensure_authorized_caller(msg::source())
+            .and_then(|()| thread_storage.report_post(thread_id, None, reporter, reason))
+            .map(StorageEvent::PostReported),
+        StorageAction::ReportReply(thread_id, reply_id, reporter, reason) => thread_storage
+            .ensure_authorized_caller(msg::source())
+            .and_then(|()| thread_storage.report_post(thread_id, Some(reply_id), reporter, reason))
+            .map(StorageEvent::PostReported),
+        StorageAction::DismissReports(post_id) => thread_storage
+            .ensure_role(msg::source(), Role::Moderator)
+            .and_then(|()| thread_storage.dismiss_reports(post_id))
+            .map(|()| StorageEvent::ReportsDismissed),
+        StorageAction::SetReportThreshold(report_threshold) => thread_storage
+            .set_report_threshold(report_threshold)
+            .map(|()| StorageEvent::ReportThresholdSet),
+        StorageAction::RemoveThread(post_id) => thread_storage
+            .remove_thread(post_id)
+            .map(|()| StorageEvent::ThreadRemoved),
+        StorageAction::RemoveReply(thread_id, reply_id) => thread_storage
+            .remove_reply(thread_id, reply_id)
+            .map(|()| StorageEvent::ReplyRemoved),
+        StorageAction::Query(query) => {
+            msg::reply(thread_storage.query(query), 0).expect("Failed to reply to Query Action");
+            return;
         }
-        StorageAction::RemoveThread(post_id) => {
-            thread_storage.remove_thread(post_id);
-            msg::reply(StorageEvent::ThreadRemoved, 0)
-                .expect("Failed to reply to RemoveThread Action");
-        }
-        StorageAction::RemoveReply(thread_id, reply_id) => {
-            thread_storage.remove_reply(thread_id, reply_id);
-            msg::reply(StorageEvent::ReplyRemoved, 0)
-                .expect("Failed to reply to RemoveReply Action");
-        }
-    }
+    };
+
+    msg::reply(result, 0).expect("Failed to reply to Storage Action");
 }
 
 #[no_mangle]
 extern fn state() {
-    let thread_storage = unsafe {
-        THREAD_STORAGE
-            .take()
-            .expect("Unexpected error in taking state")
-    };
+    // Borrow the state, the next `handle` must still find it in place
+    let thread_storage = unsafe { THREAD_STORAGE.as_ref().expect("State is not initialized") };
     let query: StorageQuery = msg::load().expect("Unable to decode query");
-    let reply = match query {
-        StorageQuery::AllRepliesWithLikes(thread_id) => {
-            let reduced_replies: Vec<(PostId, ActorId, u128)> = thread_storage
-                .threads
-                .get(&thread_id)
-                .map(|thread| {
-                    thread
-                        .replies
-                        .iter()
-                        .map(|(post_id, reply)| (*post_id, reply.post_data.owner, reply.likes))
-                        .collect::<Vec<_>>()
-                })
-                .expect("thread not found");
-
-            StorageQueryReply::AllRepliesWithLikes(reduced_replies)
-        }
-        StorageQuery::GraphRep(thread_id) => {
-            let graph_rep = thread_storage
-                .threads
-                .get(&thread_id)
-                .map(|thread| &thread.graph_rep)
-                .expect("thread not found");
-
-            StorageQueryReply::GraphRep(graph_rep.clone())
-        }
-        StorageQuery::LikeHistoryOf(thread_id, reply_id) => {
-            let like_history = thread_storage
-                .threads
-                .get(&thread_id)
-                .and_then(|thread| thread.replies.iter().find(|(id, _)| *id == reply_id))
-                .map(|(_, reply)| &reply.like_history);
-            StorageQueryReply::LikeHistoryOf(like_history.unwrap().clone())
-        }
-        StorageQuery::AllThreadsFE => {
-            let threads_fe: Vec<(QueryThread, Option<Post>)> = thread_storage
-                .threads
-                .iter()
-                .map(|(post_id, thread)| {
-                    let featured_reply_fe = thread_storage
-                        .get_featured_reply(*post_id)
-                        .map(|reply| reply.post_data.clone());
-
-                    let query_thread: QueryThread = QueryThread {
-                        post_data: thread.post_data.clone(),
-                        thread_type: thread.thread_type.clone(),
-                        thread_status: thread.thread_status.clone(),
-                    };
-
-                    (query_thread, featured_reply_fe)
-                })
-                .collect();
-
-            StorageQueryReply::AllThreadsFE(threads_fe)
-        }
-        StorageQuery::AllRepliesFE(thread_id) => {
-            let thread: &Thread = thread_storage
-                .threads
-                .get(&thread_id)
-                .expect("Failed to get thread");
-
-            let query_thread: QueryThread = QueryThread {
-                post_data: thread.post_data.clone(),
-                thread_type: thread.thread_type.clone(),
-                thread_status: thread.thread_status.clone(),
-            };
-
-            let replies_fe: Vec<QueryReply> = thread_storage
-                .threads
-                .get(&thread_id)
-                .expect("Failed to get thread")
-                .replies
-                .iter()
-                .map(|(_post_id, thread_reply)| QueryReply {
-                    post_data: thread_reply.post_data.clone(),
-                    thread_id: thread_reply.thread_id,
-                })
-                .collect();
-
-            StorageQueryReply::AllRepliesFE(query_thread, replies_fe)
-        }
-        StorageQuery::DistributedTokens(thread_id) => {
-            let distributed_tokens: u128 = thread_storage
-                .threads
-                .get(&thread_id)
-                .expect("Failed to get thread")
-                .distributed_tokens;
-
-            StorageQueryReply::DistributedTokens(distributed_tokens)
-        }
-    };
+    let reply = thread_storage.query(query);
     msg::reply(reply, 0).expect("Error in sharing state");
 }
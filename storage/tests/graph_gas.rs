@@ -0,0 +1,107 @@
+use gstd::{ActorId, Encode};
+use gtest::{Program, RunResult, System};
+use io::{FeaturedReply, Post, PostId, Thread, ThreadGraph, ThreadReply, ThreadStatus, ThreadType};
+use storage_io::{InitStorage, StorageAction, StorageError, StorageEvent, StorageQuery};
+
+const ADMIN: u64 = 10;
+const USER: u64 = 11;
+
+fn wasm_binary(name: &str) -> Vec<u8> {
+    let profile = if cfg!(debug_assertions) {
+        "debug"
+    } else {
+        "release"
+    };
+    let path = format!("../target/wasm32-unknown-unknown/{profile}/{name}.opt.wasm");
+    std::fs::read(&path).unwrap_or_else(|_| panic!("Failed to read {path}"))
+}
+
+fn assert_reply(res: &RunResult, to: u64, reply: impl Encode) {
+    assert!(!res.main_failed());
+    assert!(res.contains(&(to, reply.encode())));
+}
+
+fn post() -> Post {
+    Post {
+        post_id: 0,
+        posted_at: 0,
+        owner: ActorId::from(USER),
+        title: "Title".into(),
+        content: "Content".into(),
+        photo_url: None,
+    }
+}
+
+/// Pushes a thread with `reply_count` replies and prints the gas burned by the last reply
+/// and by querying the thread graph.
+fn measure_thread_graph_gas(reply_count: PostId) {
+    let sys = System::new();
+    let storage = Program::from_binary_with_id(&sys, 1, wasm_binary("storage"));
+
+    // The admin stands in for the logic contract
+    let init_storage = InitStorage {
+        owner: None,
+        address_logic_contract: Some(ActorId::from(ADMIN)),
+        address_reward_logic_contract: None,
+    };
+    assert!(!storage.send(ADMIN, init_storage).main_failed());
+
+    let thread = Thread {
+        post_data: post(),
+        replies: Vec::new(),
+        thread_status: ThreadStatus::Active,
+        thread_type: ThreadType::Challenge,
+        distributed_tokens: 0,
+        graph_rep: ThreadGraph::new(),
+        featured_reply: FeaturedReply::MostLiked,
+        reports: 0,
+        hidden: false,
+    };
+    let res = storage.send(ADMIN, StorageAction::PushThread(thread));
+    assert_reply(
+        &res,
+        ADMIN,
+        Ok::<_, StorageError>(StorageEvent::ThreadPush(1)),
+    );
+
+    let mut last_reply = None;
+    for reply_id in 2..reply_count + 2 {
+        let reply = ThreadReply {
+            post_data: post(),
+            likes: 0,
+            reports: 0,
+            like_history: Vec::new(),
+            thread_id: 1,
+            hidden: false,
+        };
+        let res = storage.send(ADMIN, StorageAction::PushReply(1, reply, 1));
+        assert_reply(
+            &res,
+            ADMIN,
+            Ok::<_, StorageError>(StorageEvent::ReplyPush(reply_id)),
+        );
+        last_reply = Some(res);
+    }
+    let last_reply = last_reply.expect("At least one reply is pushed");
+
+    let res = storage.send(ADMIN, StorageAction::Query(StorageQuery::GraphRep(1)));
+    assert!(!res.main_failed());
+
+    println!(
+        "{reply_count} replies: push reply {} gas, graph query {} gas",
+        last_reply.main_gas_burned().0,
+        res.main_gas_burned().0,
+    );
+}
+
+#[test]
+#[ignore = "gas benchmark, run with `cargo test --release -- --ignored --nocapture`"]
+fn thread_graph_gas_1k_replies() {
+    measure_thread_graph_gas(1_000);
+}
+
+#[test]
+#[ignore = "gas benchmark, run with `cargo test --release -- --ignored --nocapture`"]
+fn thread_graph_gas_10k_replies() {
+    measure_thread_graph_gas(10_000);
+}